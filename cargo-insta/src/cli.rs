@@ -1,13 +1,16 @@
 use std::borrow::{Borrow, Cow};
 use std::error::Error;
 use std::path::{Path, PathBuf};
-use std::{collections::HashSet, fmt};
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt,
+};
 use std::{env, fs};
 use std::{io, process};
 
 use console::{set_colors_enabled, style, Key, Term};
 use insta::_cargo_insta_support::{
-    get_cargo, is_ci, SnapshotPrinter, SnapshotUpdate, TestRunner, ToolConfig,
+    get_cargo, is_ci, SnapshotEvent, SnapshotPrinter, SnapshotUpdate, TestRunner, ToolConfig,
     UnreferencedSnapshots,
 };
 use insta::{internals::SnapshotContents, Snapshot};
@@ -18,6 +21,8 @@ use uuid::Uuid;
 
 use crate::cargo::{find_snapshot_roots, Package};
 use crate::container::{Operation, SnapshotContainer};
+use crate::jest;
+use crate::junit;
 use crate::utils::cargo_insta_version;
 use crate::utils::{err_msg, QuietExit};
 use crate::walk::{find_pending_snapshots, make_snapshot_walker, FindFlags};
@@ -78,6 +83,10 @@ enum Command {
     PendingSnapshots(PendingSnapshotsCommand),
     /// Shows a specific snapshot
     Show(ShowCommand),
+    /// Converts between insta snapshots and Jest snapshot files
+    Jest(JestCommand),
+    /// Rewrites all snapshots in the latest snapshot format
+    Migrate(MigrateCommand),
 }
 
 #[derive(Args, Debug, Clone)]
@@ -211,6 +220,9 @@ struct TestCommand {
     /// Do not pass the quiet flag (`-q`) to tests.
     #[arg(short = 'Q', long)]
     no_quiet: bool,
+    /// Write a `JUnit` XML report of all snapshot assertions to this path.
+    #[arg(long, value_name = "PATH")]
+    junit_report: Option<PathBuf>,
     /// Picks the test runner.
     #[arg(long, default_value = "auto")]
     test_runner: TestRunner,
@@ -250,6 +262,48 @@ struct ShowCommand {
     path: PathBuf,
 }
 
+#[derive(Args, Debug)]
+#[command(rename_all = "kebab-case")]
+struct MigrateCommand {
+    #[command(flatten)]
+    target_args: TargetArgs,
+    /// Do not print to stdout.
+    #[arg(short = 'q', long)]
+    quiet: bool,
+}
+
+#[derive(Args, Debug)]
+#[command(rename_all = "kebab-case")]
+struct JestCommand {
+    #[command(subcommand)]
+    action: JestAction,
+}
+
+#[derive(Subcommand, Debug)]
+#[command(rename_all = "kebab-case")]
+enum JestAction {
+    /// Writes the entries of a Jest snapshot file as insta snapshots.
+    Import {
+        /// The Jest snapshot file (`__snapshots__/*.snap`).
+        jest_file: PathBuf,
+        /// The folder to write the insta snapshots to.
+        snapshot_dir: PathBuf,
+        /// The module prefix of the snapshot file names.
+        #[arg(long)]
+        prefix: Option<String>,
+    },
+    /// Writes the insta snapshots of a folder to a Jest snapshot file.
+    Export {
+        /// The folder with the insta snapshots.
+        snapshot_dir: PathBuf,
+        /// The Jest snapshot file to write.
+        jest_file: PathBuf,
+        /// Only export the snapshots with this module prefix.
+        #[arg(long)]
+        prefix: Option<String>,
+    },
+}
+
 #[allow(clippy::too_many_arguments)]
 fn query_snapshot(
     workspace_root: &Path,
@@ -263,7 +317,9 @@ fn query_snapshot(
     snapshot_file: Option<&Path>,
     show_info: &mut bool,
     show_diff: &mut bool,
+    tool_config: &ToolConfig,
 ) -> Result<Operation, Box<dyn Error>> {
+    let diff_algorithm = tool_config.diff_algorithm();
     loop {
         term.clear_screen()?;
 
@@ -281,6 +337,8 @@ fn query_snapshot(
         printer.set_line(line);
         printer.set_show_info(*show_info);
         printer.set_show_diff(*show_diff);
+        printer.set_diff_engine(&diff_algorithm);
+        printer.set_diff_context(tool_config.diff_context());
         printer.print();
 
         println!();
@@ -585,6 +643,7 @@ fn process_snapshots(
                     snapshot_file.as_deref(),
                     &mut show_info,
                     &mut show_diff,
+                    &loc.tool_config,
                 )?,
             };
             match op {
@@ -602,6 +661,7 @@ fn process_snapshots(
             }
         }
         snapshot_container.commit()?;
+        emit_review_events(loc, snapshot_container);
     }
 
     if op.is_none() {
@@ -633,6 +693,41 @@ fn process_snapshots(
     Ok(())
 }
 
+/// Tells editor integrations about the snapshots that were accepted or
+/// rejected.
+fn emit_review_events(loc: &LocationInfo<'_>, snapshot_container: &mut SnapshotContainer) {
+    let pending_file = snapshot_container.pending_file().to_path_buf();
+    let target_file = snapshot_container.target_file().to_path_buf();
+    let snapshot_file = snapshot_container.snapshot_file().map(|x| x.to_path_buf());
+    for snapshot_ref in snapshot_container.iter_snapshots() {
+        let event = match snapshot_ref.op {
+            Operation::Accept => "accepted",
+            Operation::Reject => "rejected",
+            Operation::Skip => continue,
+        };
+        let metadata = snapshot_ref.new.metadata();
+        // inline snapshots live in the source file itself
+        let source_file = match snapshot_file {
+            Some(_) => metadata.source().map(|x| loc.workspace_root.join(x)),
+            None => Some(target_file.clone()),
+        };
+        // the name of a snapshot loaded from a `.snap.new` file keeps `.snap`
+        let snapshot_name = snapshot_ref
+            .new
+            .snapshot_name()
+            .map(|x| x.strip_suffix(".snap").unwrap_or(x));
+        SnapshotEvent {
+            event,
+            snapshot_name,
+            snapshot_file: snapshot_file.as_deref(),
+            pending_file: Some(&pending_file),
+            source_file: source_file.as_deref(),
+            line: snapshot_ref.line.or_else(|| metadata.assertion_line()),
+        }
+        .emit();
+    }
+}
+
 /// Run the tests
 fn test_run(mut cmd: TestCommand, color: ColorWhen) -> Result<(), Box<dyn Error>> {
     let loc = handle_target_args(&cmd.target_args, &cmd.test_runner_options.package)?;
@@ -647,28 +742,22 @@ fn test_run(mut cmd: TestCommand, color: ColorWhen) -> Result<(), Box<dyn Error>
     // Based on any configs in the config file, update the test command. Default
     // is `SnapshotUpdate::Auto`.
     match loc.tool_config.snapshot_update() {
-        SnapshotUpdate::Auto => {
-            if is_ci() {
-                cmd.check = true;
-            }
+        SnapshotUpdate::Auto if is_ci() => {
+            cmd.check = true;
         }
-        SnapshotUpdate::New | SnapshotUpdate::No => {}
-        SnapshotUpdate::Always => {
-            if !cmd.accept && !cmd.accept_unseen && !cmd.review {
-                cmd.review = false;
-                cmd.accept = true;
-            }
+        SnapshotUpdate::Always if !cmd.accept && !cmd.accept_unseen && !cmd.review => {
+            cmd.review = false;
+            cmd.accept = true;
         }
-        SnapshotUpdate::Unseen => {
-            if !cmd.accept {
-                cmd.accept_unseen = true;
-                cmd.review = true;
-                cmd.accept = false;
-            }
+        SnapshotUpdate::Unseen if !cmd.accept => {
+            cmd.accept_unseen = true;
+            cmd.review = true;
+            cmd.accept = false;
         }
         SnapshotUpdate::Force => {
             cmd.force_update_snapshots = true;
         }
+        _ => {}
     }
     // `--force-update-snapshots` implies `--accept`
     if cmd.force_update_snapshots {
@@ -717,6 +806,16 @@ fn test_run(mut cmd: TestCommand, color: ColorWhen) -> Result<(), Box<dyn Error>
     let (mut proc, snapshot_ref_file, prevents_doc_run) =
         prepare_test_runner(&cmd, test_runner, color, &[], None, &loc)?;
 
+    // the runtime records the outcome of every assertion into this file, from
+    // which the report is written once the tests are done
+    let assertion_results_file = cmd
+        .junit_report
+        .as_ref()
+        .map(|_| env::temp_dir().join(Uuid::new_v4().to_string()));
+    if let Some(ref path) = assertion_results_file {
+        proc.env("INSTA_ASSERTION_RESULTS_FILE", path);
+    }
+
     if let Some(workspace_root) = &cmd.target_args.workspace_root {
         proc.current_dir(workspace_root);
     }
@@ -740,9 +839,18 @@ fn test_run(mut cmd: TestCommand, color: ColorWhen) -> Result<(), Box<dyn Error>
             snapshot_ref_file.as_deref(),
             &loc,
         )?;
+        if let Some(ref path) = assertion_results_file {
+            proc.env("INSTA_ASSERTION_RESULTS_FILE", path);
+        }
         success = success && proc.status()?.success();
     }
 
+    if let (Some(report_file), Some(results_file)) = (&cmd.junit_report, &assertion_results_file) {
+        let rv = junit::write_report(results_file, report_file);
+        fs::remove_file(results_file).ok();
+        rv?;
+    }
+
     if !success && cmd.review {
         eprintln!(
             "{} non snapshot tests failed, skipping review",
@@ -1122,6 +1230,77 @@ fn show_cmd(cmd: ShowCommand) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+fn jest_cmd(cmd: JestCommand) -> Result<(), Box<dyn Error>> {
+    match cmd.action {
+        JestAction::Import {
+            jest_file,
+            snapshot_dir,
+            prefix,
+        } => {
+            let paths = jest::import(&jest_file, &snapshot_dir, prefix.as_deref())?;
+            println!("{}:", style("imported").green());
+            for path in paths {
+                println!("  {}", path.display());
+            }
+        }
+        JestAction::Export {
+            snapshot_dir,
+            jest_file,
+            prefix,
+        } => {
+            let count = jest::export(&snapshot_dir, &jest_file, prefix.as_deref())?;
+            println!(
+                "{}: {} snapshots to {}",
+                style("exported").green(),
+                count,
+                jest_file.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn migrate_cmd(cmd: MigrateCommand) -> Result<(), Box<dyn Error>> {
+    let loc = handle_target_args(&cmd.target_args, &[])?;
+    // collect the snapshots first, so they are migrated in a stable order
+    // and only once even if snapshot roots overlap.
+    let mut paths = BTreeSet::new();
+    for package in &loc.packages {
+        for root in find_snapshot_roots(package) {
+            for entry in make_snapshot_walker(&root, &loc.exts, loc.find_flags) {
+                let path = entry?.into_path();
+                let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                if loc
+                    .exts
+                    .iter()
+                    .any(|ext| file_name.ends_with(&format!(".{}", ext)))
+                {
+                    paths.insert(path);
+                }
+            }
+        }
+    }
+
+    let mut migrated = 0;
+    for path in &paths {
+        if Snapshot::migrate_file(path)? {
+            if !cmd.quiet {
+                println!("{}: {}", style("migrated").green(), path.display());
+            }
+            migrated += 1;
+        }
+    }
+    if !cmd.quiet {
+        println!(
+            "{}: {} of {} snapshots rewritten",
+            style("done").bold(),
+            migrated,
+            paths.len()
+        );
+    }
+    Ok(())
+}
+
 fn pending_snapshots_cmd(cmd: PendingSnapshotsCommand) -> Result<(), Box<dyn Error>> {
     #[derive(Serialize, Debug)]
     #[serde(rename_all = "snake_case", tag = "type")]
@@ -1277,6 +1456,8 @@ pub(crate) fn run() -> Result<(), Box<dyn Error>> {
         }
         Command::Test(cmd) => test_run(cmd, opts.color.unwrap_or(ColorWhen::Auto)),
         Command::Show(cmd) => show_cmd(cmd),
+        Command::Jest(cmd) => jest_cmd(cmd),
+        Command::Migrate(cmd) => migrate_cmd(cmd),
         Command::PendingSnapshots(cmd) => pending_snapshots_cmd(cmd),
     }
 }
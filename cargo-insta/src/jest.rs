@@ -0,0 +1,312 @@
+//! Conversion between insta snapshots and Jest snapshot files.
+//!
+//! A Jest snapshot file is a JavaScript module with one `exports[...]`
+//! assignment per snapshot:
+//!
+//! ```text
+//! // Jest Snapshot v1, https://goo.gl/fbAQLP
+//!
+//! exports[`adds numbers 1`] = `
+//! Object {
+//!   "sum": 3,
+//! }
+//! `;
+//! ```
+//!
+//! Every entry corresponds to one insta snapshot file.  The key is kept in
+//! the `info` of imported snapshots so that exporting them again yields the
+//! same keys.
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use insta::internals::{Content, SnapshotContents};
+use insta::Snapshot;
+
+use crate::utils::err_msg;
+
+const HEADER: &str = "// Jest Snapshot v1, https://goo.gl/fbAQLP";
+
+/// Converts the entries of a Jest snapshot file into insta snapshots in
+/// `snapshot_dir`.
+///
+/// The snapshot files are named `<prefix>__<name>.snap` (or `<name>.snap`
+/// without a prefix), just like insta names snapshots of a test module.
+/// Returns the paths of the written snapshots.
+pub(crate) fn import(
+    jest_file: &Path,
+    snapshot_dir: &Path,
+    prefix: Option<&str>,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let source = fs::read_to_string(jest_file)?;
+    let mut rv = Vec::new();
+    let mut names = HashSet::new();
+    for (key, value) in parse(&source)? {
+        let name = unique_snapshot_name(&key, &mut names);
+        let path = snapshot_dir.join(match prefix {
+            Some(prefix) => format!("{}__{}.snap", prefix, name),
+            None => format!("{}.snap", name),
+        });
+        let snapshot = Snapshot::from_text(
+            prefix.unwrap_or(""),
+            &name,
+            Some(Content::Map(vec![(
+                Content::from("jest_key"),
+                Content::from(key),
+            )])),
+            value,
+        );
+        snapshot.save(&path)?;
+        rv.push(path);
+    }
+    Ok(rv)
+}
+
+/// Writes the snapshots in `snapshot_dir` to a Jest snapshot file.
+///
+/// With a prefix only the snapshots of that module are exported.  Binary
+/// snapshots cannot be represented and are skipped.  Returns the number of
+/// exported snapshots.
+pub(crate) fn export(
+    snapshot_dir: &Path,
+    jest_file: &Path,
+    prefix: Option<&str>,
+) -> Result<usize, Box<dyn Error>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(snapshot_dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|x| x.to_str()) != Some("snap") {
+            continue;
+        }
+        let snapshot = Snapshot::from_file(&path)?;
+        if prefix.map_or(false, |x| x != snapshot.module_name()) {
+            continue;
+        }
+        let contents = match snapshot.contents() {
+            SnapshotContents::Text(contents) => contents,
+            SnapshotContents::Binary(_) => continue,
+        };
+        let key = match snapshot.metadata().private_info() {
+            Some(Content::Map(map)) => map
+                .iter()
+                .find(|x| x.0.as_str() == Some("jest_key"))
+                .and_then(|x| x.1.as_str())
+                .map(|x| x.to_string()),
+            _ => None,
+        }
+        .unwrap_or_else(|| key_of_snapshot_name(snapshot.snapshot_name().unwrap_or("")));
+        entries.push((key, contents.to_string()));
+    }
+    entries.sort();
+    if let Some(folder) = jest_file.parent() {
+        fs::create_dir_all(folder)?;
+    }
+    fs::write(jest_file, render(&entries))?;
+    Ok(entries.len())
+}
+
+/// Parses the entries of a Jest snapshot file into keys and values.
+fn parse(source: &str) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let mut rv = Vec::new();
+    let mut rest = source;
+    loop {
+        rest = skip_comments(rest);
+        if rest.is_empty() {
+            return Ok(rv);
+        }
+        rest = rest
+            .strip_prefix("exports[")
+            .ok_or_else(|| err_msg("invalid jest snapshot: expected exports[...]"))?;
+        let (key, after) = parse_backtick_string(rest)?;
+        rest = after
+            .trim_start()
+            .strip_prefix(']')
+            .and_then(|x| x.trim_start().strip_prefix('='))
+            .ok_or_else(|| err_msg("invalid jest snapshot: expected ] ="))?
+            .trim_start();
+        let (value, after) = parse_backtick_string(rest)?;
+        rest = after
+            .trim_start()
+            .strip_prefix(';')
+            .ok_or_else(|| err_msg("invalid jest snapshot: expected ;"))?;
+        rv.push((key, remove_extra_line_breaks(&value).to_string()));
+    }
+}
+
+/// Renders the entries as a Jest snapshot file.
+fn render(entries: &[(String, String)]) -> String {
+    let mut rv = String::from(HEADER);
+    rv.push('\n');
+    for (key, value) in entries {
+        rv.push_str(&format!(
+            "\nexports[{}] = {};\n",
+            backtick_string(key),
+            backtick_string(&add_extra_line_breaks(value))
+        ));
+    }
+    rv
+}
+
+fn skip_comments(mut s: &str) -> &str {
+    loop {
+        s = s.trim_start();
+        if let Some(rest) = s.strip_prefix("//") {
+            s = rest.split_once('\n').map_or("", |x| x.1);
+        } else if let Some(rest) = s.strip_prefix("/*") {
+            s = rest.split_once("*/").map_or("", |x| x.1);
+        } else {
+            return s;
+        }
+    }
+}
+
+/// Parses a template literal.  Jest only ever escapes backslashes, backticks
+/// and `${`, by prefixing them with a backslash.
+fn parse_backtick_string(s: &str) -> Result<(String, &str), Box<dyn Error>> {
+    let mut chars = s
+        .strip_prefix('`')
+        .ok_or_else(|| err_msg("invalid jest snapshot: expected template literal"))?
+        .char_indices();
+    let mut rv = String::new();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\\' => rv.extend(chars.next().map(|x| x.1)),
+            '`' => return Ok((rv, &s[idx + 2..])),
+            c => rv.push(c),
+        }
+    }
+    Err(err_msg(
+        "invalid jest snapshot: unterminated template literal",
+    ))
+}
+
+fn backtick_string(s: &str) -> String {
+    let mut rv = String::from("`");
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' || c == '`' || (c == '$' && chars.peek() == Some(&'{')) {
+            rv.push('\\');
+        }
+        rv.push(c);
+    }
+    rv.push('`');
+    rv
+}
+
+/// Jest puts multi-line values on lines of their own.
+fn add_extra_line_breaks(s: &str) -> String {
+    if s.contains('\n') {
+        format!("\n{}\n", s)
+    } else {
+        s.to_string()
+    }
+}
+
+fn remove_extra_line_breaks(s: &str) -> &str {
+    if s.len() > 2 && s.starts_with('\n') && s.ends_with('\n') {
+        &s[1..s.len() - 1]
+    } else {
+        s
+    }
+}
+
+/// Turns a Jest key (the test name followed by a counter) into a snapshot
+/// name, numbering repeated snapshots like insta does.
+fn snapshot_name_of_key(key: &str) -> String {
+    let (name, counter) = match key.rsplit_once(' ') {
+        Some((name, counter)) if counter.parse::<u32>().is_ok() => (name, counter),
+        _ => (key, "1"),
+    };
+    let mut rv = String::new();
+    for c in name.chars() {
+        let c = if c.is_alphanumeric() || c == '-' {
+            c
+        } else {
+            '_'
+        };
+        // `__` separates the module from the name in file names
+        if !(c == '_' && rv.ends_with('_')) {
+            rv.push(c);
+        }
+    }
+    let mut rv = rv.trim_matches('_').to_string();
+    if rv.is_empty() {
+        rv.push_str("unnamed");
+    }
+    if counter != "1" {
+        rv.push('-');
+        rv.push_str(counter);
+    }
+    rv
+}
+
+/// Like [`snapshot_name_of_key`] but numbers names already in `names`.
+///
+/// Different keys can map to the same name (`a b` and `a_b`), which would
+/// otherwise overwrite each other's snapshot file.  The original key is kept
+/// in the snapshot, so exporting is not affected by the extra counter.
+fn unique_snapshot_name(key: &str, names: &mut HashSet<String>) -> String {
+    let name = snapshot_name_of_key(key);
+    let mut rv = name.clone();
+    let mut counter = 2;
+    while !names.insert(rv.clone()) {
+        rv = format!("{}-{}", name, counter);
+        counter += 1;
+    }
+    rv
+}
+
+/// The reverse of [`snapshot_name_of_key`], for snapshots created by insta.
+fn key_of_snapshot_name(name: &str) -> String {
+    match name.rsplit_once('-') {
+        Some((name, counter)) if counter.parse::<u32>().is_ok() => {
+            format!("{} {}", name, counter)
+        }
+        _ => format!("{} 1", name),
+    }
+}
+
+#[test]
+fn test_jest_roundtrip() {
+    let source = r#"// Jest Snapshot v1, https://goo.gl/fbAQLP
+
+exports[`adds \`numbers\` 1`] = `
+Object {
+  "path": "C:\\temp",
+  "template": "\${x}",
+}
+`;
+
+exports[`adds \`numbers\` 2`] = `"single line"`;
+"#;
+    let entries = parse(source).unwrap();
+    assert_eq!(
+        entries,
+        vec![
+            (
+                "adds `numbers` 1".to_string(),
+                "Object {\n  \"path\": \"C:\\temp\",\n  \"template\": \"${x}\",\n}".to_string()
+            ),
+            (
+                "adds `numbers` 2".to_string(),
+                "\"single line\"".to_string()
+            ),
+        ]
+    );
+    assert_eq!(render(&entries), source);
+    assert_eq!(snapshot_name_of_key(&entries[0].0), "adds_numbers");
+    assert_eq!(snapshot_name_of_key(&entries[1].0), "adds_numbers-2");
+    assert_eq!(key_of_snapshot_name("vector-2"), "vector 2");
+    assert_eq!(key_of_snapshot_name("vector"), "vector 1");
+}
+
+#[test]
+fn test_unique_snapshot_name() {
+    let mut names = HashSet::new();
+    assert_eq!(unique_snapshot_name("a b 1", &mut names), "a_b");
+    assert_eq!(unique_snapshot_name("a_b 1", &mut names), "a_b-2");
+    assert_eq!(unique_snapshot_name("a b 2", &mut names), "a_b-2-2");
+    assert_eq!(unique_snapshot_name("!!! 1", &mut names), "unnamed");
+    assert_eq!(unique_snapshot_name("? 1", &mut names), "unnamed-2");
+}
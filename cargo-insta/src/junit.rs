@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// The outcome of a single assertion, as recorded by the insta runtime.
+#[derive(Deserialize, Debug)]
+struct AssertionResult {
+    outcome: String,
+    snapshot_name: String,
+    module_path: String,
+    assertion_file: String,
+    assertion_line: u32,
+    diff: Option<String>,
+}
+
+/// Turns the assertion results recorded during a test run into a `JUnit` XML
+/// report.
+///
+/// Every assertion becomes a test case, grouped into one test suite per
+/// module.  Failing and new snapshots are reported as failures with the diff
+/// attached.
+pub(crate) fn write_report(results_file: &Path, report_file: &Path) -> Result<(), Box<dyn Error>> {
+    // the file does not exist if no assertion ran at all
+    let results = fs::read_to_string(results_file).unwrap_or_default();
+    let mut suites = BTreeMap::<String, Vec<AssertionResult>>::new();
+    for line in results.lines().filter(|x| !x.is_empty()) {
+        let result: AssertionResult = serde_json::from_str(line)?;
+        suites
+            .entry(result.module_path.clone())
+            .or_default()
+            .push(result);
+    }
+
+    let total = suites.values().map(|x| x.len()).sum::<usize>();
+    let failures =
+        |results: &[AssertionResult]| results.iter().filter(|x| x.outcome != "pass").count();
+
+    let mut out = String::new();
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<testsuites name="insta" tests="{}" failures="{}">"#,
+        total,
+        suites.values().map(|x| failures(x)).sum::<usize>(),
+    )?;
+    for (module_path, results) in suites.iter_mut() {
+        // tests run in parallel, so sort for a stable report
+        results.sort_by(|a, b| {
+            (&a.assertion_file, a.assertion_line, &a.snapshot_name).cmp(&(
+                &b.assertion_file,
+                b.assertion_line,
+                &b.snapshot_name,
+            ))
+        });
+        writeln!(
+            out,
+            r#"  <testsuite name="{}" tests="{}" failures="{}">"#,
+            escape(module_path),
+            results.len(),
+            failures(results),
+        )?;
+        for result in results.iter() {
+            write!(
+                out,
+                r#"    <testcase name="{}" classname="{}" file="{}" line="{}""#,
+                escape(&result.snapshot_name),
+                escape(module_path),
+                escape(&result.assertion_file),
+                result.assertion_line,
+            )?;
+            let message = match result.outcome.as_str() {
+                "pass" => {
+                    writeln!(out, "/>")?;
+                    continue;
+                }
+                "new" => "new snapshot",
+                _ => "snapshot does not match",
+            };
+            writeln!(out, ">")?;
+            writeln!(
+                out,
+                r#"      <failure type="{}" message="{}">{}</failure>"#,
+                escape(&result.outcome),
+                message,
+                escape(result.diff.as_deref().unwrap_or("")),
+            )?;
+            writeln!(out, "    </testcase>")?;
+        }
+        writeln!(out, "  </testsuite>")?;
+    }
+    writeln!(out, "</testsuites>")?;
+
+    if let Some(parent) = report_file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(report_file, out)?;
+    Ok(())
+}
+
+/// Escapes a string for use in XML text and attribute values.
+fn escape(s: &str) -> String {
+    let mut rv = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => rv.push_str("&amp;"),
+            '<' => rv.push_str("&lt;"),
+            '>' => rv.push_str("&gt;"),
+            '"' => rv.push_str("&quot;"),
+            '\'' => rv.push_str("&apos;"),
+            // XML 1.0 cannot represent most control characters, not even
+            // as character references.
+            '\t' | '\n' | '\r' => rv.push(c),
+            c if c < ' ' => rv.push('\u{fffd}'),
+            c => rv.push(c),
+        }
+    }
+    rv
+}
+
+#[test]
+fn test_escape() {
+    assert_eq!(
+        escape("<a href=\"x\">&'\x1b\n"),
+        "&lt;a href=&quot;x&quot;&gt;&amp;&apos;\u{fffd}\n"
+    );
+}
@@ -18,6 +18,8 @@ mod cargo;
 mod cli;
 mod container;
 mod inline;
+mod jest;
+mod junit;
 mod utils;
 mod walk;
 
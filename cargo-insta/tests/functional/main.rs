@@ -694,6 +694,144 @@ Unused snapshot
     ");
 }
 
+#[test]
+fn test_junit_report() {
+    let test_project = TestFiles::new()
+        .add_cargo_toml("test_junit_report")
+        .add_file(
+            "src/lib.rs",
+            r#"
+#[test]
+fn test_passing() {
+    insta::assert_snapshot!("passing", "matches");
+}
+
+#[test]
+fn test_failing() {
+    insta::assert_snapshot!("failing", "new <value>");
+}
+
+#[test]
+fn test_new() {
+    insta::assert_snapshot!("new", "not there yet");
+}
+"#
+            .to_string(),
+        )
+        .add_file(
+            "src/snapshots/test_junit_report__passing.snap",
+            r#"---
+source: src/lib.rs
+expression: "\"matches\""
+---
+matches
+"#
+            .to_string(),
+        )
+        .add_file(
+            "src/snapshots/test_junit_report__failing.snap",
+            r#"---
+source: src/lib.rs
+expression: "\"new <value>\""
+---
+old <value>
+"#
+            .to_string(),
+        )
+        .create_project();
+
+    let output = test_project
+        .insta_cmd()
+        .args(["test", "--junit-report", "target/junit.xml"])
+        .output()
+        .unwrap();
+
+    // the report does not change how pending snapshots are reported
+    assert!(!output.status.success());
+
+    let report = fs::read_to_string(test_project.workspace_dir.join("target/junit.xml")).unwrap();
+    assert_snapshot!(report, @r#"
+    <?xml version="1.0" encoding="UTF-8"?>
+    <testsuites name="insta" tests="3" failures="2">
+      <testsuite name="test_junit_report" tests="3" failures="2">
+        <testcase name="passing" classname="test_junit_report" file="src/lib.rs" line="4"/>
+        <testcase name="failing" classname="test_junit_report" file="src/lib.rs" line="9">
+          <failure type="fail" message="snapshot does not match">--- old snapshot
+    +++ new results
+    @@ -1 +1 @@
+    -old &lt;value&gt;
+    +new &lt;value&gt;
+    </failure>
+        </testcase>
+        <testcase name="new" classname="test_junit_report" file="src/lib.rs" line="14">
+          <failure type="new" message="new snapshot">--- old snapshot
+    +++ new results
+    @@ -0,0 +1 @@
+    +not there yet
+    </failure>
+        </testcase>
+      </testsuite>
+    </testsuites>
+    "#);
+}
+
+#[test]
+fn test_snapshot_events() {
+    let test_project = TestFiles::new()
+        .add_cargo_toml("test_snapshot_events")
+        .add_file(
+            "src/lib.rs",
+            r#"
+#[test]
+fn test_file() {
+    insta::assert_snapshot!("file", "new value");
+}
+
+#[test]
+fn test_inline() {
+    insta::assert_snapshot!("new value", @"");
+}
+"#
+            .to_string(),
+        )
+        .create_project();
+
+    let events_file = test_project.workspace_dir.join("events.jsonl");
+    let output = test_project
+        .insta_cmd()
+        .args(["test"])
+        .env("INSTA_EVENTS", &events_file)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let output = test_project
+        .insta_cmd()
+        .args(["accept"])
+        .env("INSTA_EVENTS", &events_file)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // paths are escaped in JSON
+    let workspace_dir = test_project.workspace_dir.canonicalize().unwrap();
+    let events = fs::read_to_string(&events_file)
+        .unwrap()
+        .replace(
+            &workspace_dir.display().to_string().replace('\\', r"\\"),
+            "[PROJECT]",
+        )
+        .replace(r"\\", "/");
+    // tests run in parallel, so sort for a stable order
+    let events = events.lines().sorted().join("\n");
+    assert_snapshot!(events, @r#"
+    {"event":"accepted","kind":"file","snapshot_name":"file","snapshot_file":"[PROJECT]/src/snapshots/test_snapshot_events__file.snap","pending_file":"[PROJECT]/src/snapshots/test_snapshot_events__file.snap.new","source_file":"[PROJECT]/src/lib.rs","line":4}
+    {"event":"accepted","kind":"inline","snapshot_name":"inline","snapshot_file":null,"pending_file":"[PROJECT]/src/.lib.rs.pending-snap","source_file":"[PROJECT]/src/lib.rs","line":9}
+    {"event":"created","kind":"file","snapshot_name":"file","snapshot_file":"[PROJECT]/src/snapshots/test_snapshot_events__file.snap","pending_file":"[PROJECT]/src/snapshots/test_snapshot_events__file.snap.new","source_file":"[PROJECT]/src/lib.rs","line":4}
+    {"event":"created","kind":"inline","snapshot_name":"inline","snapshot_file":null,"pending_file":"[PROJECT]/src/.lib.rs.pending-snap","source_file":"[PROJECT]/src/lib.rs","line":9}
+    "#);
+}
+
 #[test]
 fn test_hidden_snapshots() {
     let test_project = TestFiles::new()
@@ -819,6 +957,96 @@ existing snapshot
     assert!(output.status.success());
 }
 
+#[test]
+fn test_migrate() {
+    let test_project = TestFiles::new()
+        .add_cargo_toml("test_migrate")
+        .add_file("src/lib.rs", "".to_string())
+        .add_file(
+            "src/snapshots/test_migrate__legacy.snap",
+            r#"Created: 2019-01-01T00:00:00Z
+Creator: insta@0.1.0
+Expression: "legacy"
+Source: src/lib.rs
+
+legacy
+"#
+            .to_string(),
+        )
+        .add_file(
+            "src/snapshots/test_migrate__outdated.snap",
+            r#"---
+source: src/lib.rs
+assertion_line: 3
+expression: "\"outdated\""
+snapshot_kind: text
+---
+outdated
+
+"#
+            .to_string(),
+        )
+        .add_file(
+            "src/snapshots/test_migrate__current.snap",
+            r#"---
+source: src/lib.rs
+expression: "\"current\""
+---
+current
+"#
+            .to_string(),
+        )
+        .create_project();
+
+    let output = test_project
+        .insta_cmd()
+        .args(["migrate"])
+        .stdout(std::process::Stdio::piped())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("2 of 3 snapshots rewritten"));
+
+    assert_snapshot!(test_project.diff("src/snapshots/test_migrate__legacy.snap"), @r#"
+    --- Original: src/snapshots/test_migrate__legacy.snap
+    +++ Updated: src/snapshots/test_migrate__legacy.snap
+    @@ -1,6 +1,5 @@
+    -Created: 2019-01-01T00:00:00Z
+    -Creator: insta@0.1.0
+    -Expression: "legacy"
+    -Source: src/lib.rs
+    -
+    +---
+    +source: src/lib.rs
+    +expression: "\"legacy\""
+    +---
+     legacy
+    "#);
+    assert_snapshot!(test_project.diff("src/snapshots/test_migrate__outdated.snap"), @r#"
+    --- Original: src/snapshots/test_migrate__outdated.snap
+    +++ Updated: src/snapshots/test_migrate__outdated.snap
+    @@ -1,8 +1,5 @@
+     ---
+     source: src/lib.rs
+    -assertion_line: 3
+     expression: "\"outdated\""
+    -snapshot_kind: text
+     ---
+     outdated
+    -
+    "#);
+
+    // migrating again is a no-op
+    let output = test_project
+        .insta_cmd()
+        .args(["migrate"])
+        .stdout(std::process::Stdio::piped())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("0 of 3 snapshots rewritten"));
+}
+
 #[test]
 fn test_ignored_snapshots() {
     let test_project = TestFiles::new()
@@ -0,0 +1,261 @@
+//! Capturing of `tracing` events and `log` records.
+//!
+//! Both capture functions render one line per event, in the order they were
+//! emitted.  Timestamps, thread ids and span ids are never part of the output
+//! so the result is stable across runs and can be snapshotted directly.
+
+#[cfg(feature = "tracing")]
+pub use self::tracing_capture::capture_tracing;
+
+#[cfg(feature = "log")]
+pub use self::log_capture::capture_log;
+
+#[cfg(feature = "tracing")]
+mod tracing_capture {
+    use std::collections::HashMap;
+    use std::fmt::{self, Write};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// Collects the message and the fields of an event or span.
+    #[derive(Default)]
+    struct FieldVisitor {
+        message: String,
+        fields: String,
+    }
+
+    impl Visit for FieldVisitor {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "message" {
+                self.message.push_str(value);
+            } else {
+                self.record_debug(field, &value);
+            }
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            if field.name() == "message" {
+                write!(self.message, "{:?}", value).unwrap();
+            } else {
+                if !self.fields.is_empty() {
+                    self.fields.push(' ');
+                }
+                write!(self.fields, "{}={:?}", field.name(), value).unwrap();
+            }
+        }
+    }
+
+    struct SpanData {
+        name: &'static str,
+        fields: String,
+    }
+
+    /// A subscriber that renders every event into a string.
+    #[derive(Default)]
+    struct CaptureSubscriber {
+        next_id: AtomicU64,
+        spans: Mutex<HashMap<u64, SpanData>>,
+        stack: Mutex<Vec<u64>>,
+        output: Arc<Mutex<String>>,
+    }
+
+    impl Subscriber for CaptureSubscriber {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+            let mut visitor = FieldVisitor::default();
+            attrs.record(&mut visitor);
+            let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+            self.spans.lock().unwrap().insert(
+                id,
+                SpanData {
+                    name: attrs.metadata().name(),
+                    fields: visitor.fields,
+                },
+            );
+            Id::from_u64(id)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            if let Some(data) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+                let mut visitor = FieldVisitor {
+                    fields: std::mem::take(&mut data.fields),
+                    ..FieldVisitor::default()
+                };
+                values.record(&mut visitor);
+                data.fields = visitor.fields;
+            }
+        }
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut visitor = FieldVisitor::default();
+            event.record(&mut visitor);
+
+            let metadata = event.metadata();
+            let mut line = format!("{} ", metadata.level());
+            let spans = self.spans.lock().unwrap();
+            let mut in_span = false;
+            for id in self.stack.lock().unwrap().iter() {
+                if let Some(span) = spans.get(id) {
+                    if in_span {
+                        line.push(':');
+                    }
+                    line.push_str(span.name);
+                    if !span.fields.is_empty() {
+                        write!(line, "{{{}}}", span.fields).unwrap();
+                    }
+                    in_span = true;
+                }
+            }
+            if in_span {
+                line.push_str(": ");
+            }
+            write!(line, "{}: {}", metadata.target(), visitor.message).unwrap();
+            if !visitor.fields.is_empty() {
+                if !visitor.message.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(&visitor.fields);
+            }
+
+            let mut output = self.output.lock().unwrap();
+            output.push_str(line.trim_end());
+            output.push('\n');
+        }
+
+        fn enter(&self, span: &Id) {
+            self.stack.lock().unwrap().push(span.into_u64());
+        }
+
+        fn exit(&self, span: &Id) {
+            let mut stack = self.stack.lock().unwrap();
+            if let Some(idx) = stack.iter().rposition(|x| *x == span.into_u64()) {
+                stack.remove(idx);
+            }
+        }
+    }
+
+    /// Runs `f` and returns the `tracing` events it emitted.
+    ///
+    /// A capturing subscriber is installed as the default subscriber of the
+    /// current thread while `f` runs.  Every event becomes one line with the
+    /// level, the entered spans (with their fields), the target, the message
+    /// and the fields of the event:
+    ///
+    /// ```text
+    /// INFO request{method="GET"}: my_crate::server: handled status=200
+    /// ```
+    ///
+    /// Events from other threads are not captured.
+    ///
+    /// ```
+    /// let output = insta::capture_tracing(|| {
+    ///     tracing::info!(answer = 42, "computed");
+    /// });
+    /// insta::assert_snapshot!(output, @"INFO rust_out: computed answer=42");
+    /// ```
+    pub fn capture_tracing<F: FnOnce()>(f: F) -> String {
+        let subscriber = CaptureSubscriber::default();
+        let output = subscriber.output.clone();
+        tracing::subscriber::with_default(subscriber, f);
+        let rv = std::mem::take(&mut *output.lock().unwrap());
+        rv
+    }
+}
+
+#[cfg(feature = "log")]
+mod log_capture {
+    use std::cell::RefCell;
+
+    use log::{LevelFilter, Log, Metadata, Record};
+
+    thread_local! {
+        static CAPTURED: RefCell<Option<String>> = const { RefCell::new(None) };
+    }
+
+    /// The global logger, which only records while a capture is active on
+    /// the current thread.
+    struct CaptureLogger;
+
+    impl Log for CaptureLogger {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            CAPTURED.with(|x| x.borrow().is_some())
+        }
+
+        fn log(&self, record: &Record<'_>) {
+            CAPTURED.with(|x| {
+                if let Some(ref mut output) = *x.borrow_mut() {
+                    let line = format!("{} {}: {}", record.level(), record.target(), record.args());
+                    output.push_str(line.trim_end());
+                    output.push('\n');
+                }
+            });
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CaptureLogger = CaptureLogger;
+
+    /// Restores the previous capture, also when the closure panics.
+    struct CaptureGuard(Option<Option<String>>);
+
+    impl CaptureGuard {
+        fn finish(mut self) -> String {
+            let previous = self.0.take().unwrap();
+            CAPTURED.with(|x| x.replace(previous)).unwrap_or_default()
+        }
+    }
+
+    impl Drop for CaptureGuard {
+        fn drop(&mut self) {
+            if let Some(previous) = self.0.take() {
+                CAPTURED.with(|x| x.replace(previous));
+            }
+        }
+    }
+
+    /// Runs `f` and returns the `log` records it emitted.
+    ///
+    /// The first call installs a global logger, so this panics if the test
+    /// binary already installed a different one.  Only records from the
+    /// current thread are captured.  Every record becomes one line with the
+    /// level, the target and the message:
+    ///
+    /// ```text
+    /// WARN my_crate::config: falling back to defaults
+    /// ```
+    ///
+    /// ```
+    /// let output = insta::capture_log(|| {
+    ///     log::warn!("falling back to defaults");
+    /// });
+    /// insta::assert_snapshot!(output, @"WARN rust_out: falling back to defaults");
+    /// ```
+    pub fn capture_log<F: FnOnce()>(f: F) -> String {
+        static INSTALL: std::sync::Once = std::sync::Once::new();
+        INSTALL.call_once(|| {
+            if log::set_logger(&LOGGER).is_ok() {
+                log::set_max_level(LevelFilter::Trace);
+            }
+        });
+        if !std::ptr::eq(
+            log::logger() as *const dyn Log as *const u8,
+            &LOGGER as *const CaptureLogger as *const u8,
+        ) {
+            panic!("insta cannot capture log records: another logger is already installed");
+        }
+
+        let guard = CaptureGuard(Some(CAPTURED.with(|x| x.replace(Some(String::new())))));
+        f();
+        guard.finish()
+    }
+}
@@ -0,0 +1,243 @@
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// A command to snapshot.
+///
+/// This describes a subprocess for [`assert_cmd_snapshot!`](crate::assert_cmd_snapshot!).
+/// It is usually created with [`cmd!`](crate::cmd!) or converted from a
+/// [`Command`].  The command is run every time a snapshot of it is taken,
+/// and the snapshot holds the exit status, stdout and stderr:
+///
+/// ```text
+/// success: false
+/// exit_code: 2
+/// ----- stdout -----
+///
+/// ----- stderr -----
+/// error: no such file: [CWD]/missing.txt
+/// ```
+///
+/// Paths and values that differ between machines can be normalized:
+/// [`normalize_cwd`](Self::normalize_cwd) replaces the working directory with
+/// `[CWD]` and [`normalize_env`](Self::normalize_env) replaces the value of an
+/// environment variable with `[NAME]`.
+#[derive(Debug, Clone)]
+pub struct Cmd {
+    program: OsString,
+    args: Vec<OsString>,
+    envs: Vec<(OsString, Option<OsString>)>,
+    current_dir: Option<PathBuf>,
+    stdin: Option<Vec<u8>>,
+    normalize_cwd: bool,
+    normalized_env: Vec<OsString>,
+}
+
+impl Cmd {
+    /// Creates a command for `program`.
+    pub fn new<S: AsRef<OsStr>>(program: S) -> Cmd {
+        Cmd {
+            program: program.as_ref().to_os_string(),
+            args: Vec::new(),
+            envs: Vec::new(),
+            current_dir: None,
+            stdin: None,
+            normalize_cwd: false,
+            normalized_env: Vec::new(),
+        }
+    }
+
+    /// Adds an argument.
+    pub fn arg<S: AsRef<OsStr>>(mut self, arg: S) -> Cmd {
+        self.args.push(arg.as_ref().to_os_string());
+        self
+    }
+
+    /// Adds multiple arguments.
+    pub fn args<I, S>(mut self, args: I) -> Cmd
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args
+            .extend(args.into_iter().map(|x| x.as_ref().to_os_string()));
+        self
+    }
+
+    /// Sets an environment variable for the command.
+    pub fn env<K: AsRef<OsStr>, V: AsRef<OsStr>>(mut self, key: K, value: V) -> Cmd {
+        self.envs.push((
+            key.as_ref().to_os_string(),
+            Some(value.as_ref().to_os_string()),
+        ));
+        self
+    }
+
+    /// Removes an environment variable for the command.
+    pub fn env_remove<K: AsRef<OsStr>>(mut self, key: K) -> Cmd {
+        self.envs.push((key.as_ref().to_os_string(), None));
+        self
+    }
+
+    /// Sets the working directory of the command.
+    pub fn current_dir<P: Into<PathBuf>>(mut self, dir: P) -> Cmd {
+        self.current_dir = Some(dir.into());
+        self
+    }
+
+    /// Passes `data` to the command on stdin.
+    ///
+    /// Without this stdin is empty.
+    pub fn stdin<D: Into<Vec<u8>>>(mut self, data: D) -> Cmd {
+        self.stdin = Some(data.into());
+        self
+    }
+
+    /// Replaces the working directory of the command with `[CWD]` in the
+    /// output.
+    pub fn normalize_cwd(mut self) -> Cmd {
+        self.normalize_cwd = true;
+        self
+    }
+
+    /// Replaces the value of the environment variable `key` with `[KEY]` in
+    /// the output.
+    pub fn normalize_env<K: AsRef<OsStr>>(mut self, key: K) -> Cmd {
+        self.normalized_env.push(key.as_ref().to_os_string());
+        self
+    }
+
+    /// Runs the command and renders the output for a snapshot.
+    ///
+    /// Panics if the command cannot be started.
+    pub fn run(&self) -> String {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+        for (key, value) in &self.envs {
+            match value {
+                Some(value) => command.env(key, value),
+                None => command.env_remove(key),
+            };
+        }
+        if let Some(ref dir) = self.current_dir {
+            command.current_dir(dir);
+        }
+        command
+            .stdin(if self.stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        let output = command
+            .spawn()
+            .and_then(|mut child| {
+                // write from another thread so a command that does not read
+                // all of its input cannot block us.
+                let writer = match (child.stdin.take(), self.stdin.clone()) {
+                    (Some(mut pipe), Some(data)) => {
+                        Some(std::thread::spawn(move || pipe.write_all(&data).ok()))
+                    }
+                    _ => None,
+                };
+                let output = child.wait_with_output();
+                if let Some(writer) = writer {
+                    writer.join().ok();
+                }
+                output
+            })
+            .unwrap_or_else(|err| {
+                panic!(
+                    "failed to run command {}: {}",
+                    self.program.to_string_lossy(),
+                    err
+                )
+            });
+
+        let mut rv = String::new();
+        writeln!(rv, "success: {}", output.status.success()).unwrap();
+        match output.status.code() {
+            Some(code) => writeln!(rv, "exit_code: {}", code).unwrap(),
+            None => writeln!(rv, "exit_code: terminated by signal").unwrap(),
+        }
+        writeln!(rv, "----- stdout -----").unwrap();
+        rv.push_str(&self.normalize(&output.stdout));
+        if !rv.ends_with('\n') {
+            rv.push('\n');
+        }
+        writeln!(rv, "----- stderr -----").unwrap();
+        rv.push_str(&self.normalize(&output.stderr));
+        rv
+    }
+
+    fn replacements(&self) -> Vec<(String, String)> {
+        let mut rv = Vec::new();
+        if self.normalize_cwd {
+            let cwd = match self.current_dir {
+                Some(ref dir) if dir.is_absolute() => Some(dir.clone()),
+                Some(ref dir) => env::current_dir().ok().map(|x| x.join(dir)),
+                None => env::current_dir().ok(),
+            };
+            if let Some(cwd) = cwd {
+                rv.push((cwd.display().to_string(), "[CWD]".to_string()));
+            }
+        }
+        for key in &self.normalized_env {
+            let value = match self.envs.iter().rev().find(|x| &x.0 == key) {
+                Some((_, value)) => value.clone(),
+                None => env::var_os(key),
+            };
+            if let Some(value) = value.filter(|x| !x.is_empty()) {
+                rv.push((
+                    value.to_string_lossy().into_owned(),
+                    format!("[{}]", key.to_string_lossy()),
+                ));
+            }
+        }
+        // replace longer values first so that a value containing another one
+        // (say the working directory inside of `HOME`) wins.
+        rv.sort_by_key(|x| std::cmp::Reverse(x.0.len()));
+        rv
+    }
+
+    fn normalize(&self, output: &[u8]) -> String {
+        let mut rv = String::from_utf8_lossy(output).into_owned();
+        for (value, placeholder) in self.replacements() {
+            rv = rv.replace(&value, &placeholder);
+        }
+        rv
+    }
+}
+
+impl From<&Command> for Cmd {
+    /// Converts a [`Command`] into a [`Cmd`].
+    ///
+    /// Settings that cannot be read back from a [`Command`] (such as
+    /// [`Command::env_clear`] or the stdio configuration) are not carried over.
+    fn from(command: &Command) -> Cmd {
+        let mut rv = Cmd::new(command.get_program()).args(command.get_args());
+        rv.envs = command
+            .get_envs()
+            .map(|(k, v)| (k.to_os_string(), v.map(|x| x.to_os_string())))
+            .collect();
+        rv.current_dir = command.get_current_dir().map(|x| x.to_path_buf());
+        rv
+    }
+}
+
+impl From<Command> for Cmd {
+    fn from(command: Command) -> Cmd {
+        Cmd::from(&command)
+    }
+}
+
+impl From<&Cmd> for Cmd {
+    fn from(cmd: &Cmd) -> Cmd {
+        cmd.clone()
+    }
+}
@@ -1,4 +1,5 @@
 use std::fmt::{Display, Write};
+use std::slice;
 
 use crate::content::Content;
 
@@ -21,6 +22,15 @@ pub enum Format {
     Pretty,
 }
 
+/// Pending work of the [`Serializer`].
+enum Task<'a> {
+    Value(&'a Content),
+    Seq(slice::Iter<'a, Content>, bool),
+    Fields(slice::Iter<'a, (&'static str, Content)>, bool),
+    Map(slice::Iter<'a, (Content, Content)>, bool),
+    End(char, bool),
+}
+
 /// Serializes a serializable to JSON.
 pub struct Serializer {
     out: String,
@@ -92,116 +102,151 @@ impl Serializer {
         }
     }
 
-    fn serialize_array(&mut self, items: &[Content]) {
+    fn start_array<'a>(&mut self, stack: &mut Vec<Task<'a>>, items: &'a [Content]) {
         self.start_container('[');
-        for (idx, item) in items.iter().enumerate() {
-            self.write_comma(idx == 0);
-            self.serialize(item);
-        }
-        self.end_container(']', items.is_empty());
+        stack.push(Task::End(']', items.is_empty()));
+        stack.push(Task::Seq(items.iter(), true));
     }
 
-    fn serialize_object(&mut self, fields: &[(&str, Content)]) {
+    fn start_object<'a>(
+        &mut self,
+        stack: &mut Vec<Task<'a>>,
+        fields: &'a [(&'static str, Content)],
+    ) {
         self.start_container('{');
-        for (idx, (key, value)) in fields.iter().enumerate() {
-            self.write_comma(idx == 0);
-            self.write_escaped_str(key);
-            self.write_colon();
-            self.serialize(value);
+        stack.push(Task::End('}', fields.is_empty()));
+        stack.push(Task::Fields(fields.iter(), true));
+    }
+
+    /// Starts the single entry object an enum variant serializes to.
+    fn start_variant(&mut self, stack: &mut Vec<Task<'_>>, variant: &str) {
+        self.start_container('{');
+        self.write_comma(true);
+        self.write_escaped_str(variant);
+        self.write_colon();
+        stack.push(Task::End('}', false));
+    }
+
+    fn write_map_key(&mut self, key: &Content) {
+        let real_key = key.resolve_inner();
+        if let Content::String(ref s) = real_key {
+            self.write_escaped_str(s);
+        } else if let Some(num) = real_key.as_i64() {
+            self.write_escaped_str(&num.to_string());
+        } else if let Some(num) = real_key.as_i128() {
+            self.write_escaped_str(&num.to_string());
+        } else {
+            panic!("cannot serialize maps without string keys to JSON");
         }
-        self.end_container('}', fields.is_empty());
     }
 
     pub fn serialize(&mut self, value: &Content) {
-        match value {
-            Content::Bool(true) => self.write_str("true"),
-            Content::Bool(false) => self.write_str("false"),
-            Content::U8(n) => write!(self.out, "{}", n).unwrap(),
-            Content::U16(n) => write!(self.out, "{}", n).unwrap(),
-            Content::U32(n) => write!(self.out, "{}", n).unwrap(),
-            Content::U64(n) => write!(self.out, "{}", n).unwrap(),
-            Content::U128(n) => write!(self.out, "{}", n).unwrap(),
-            Content::I8(n) => write!(self.out, "{}", n).unwrap(),
-            Content::I16(n) => write!(self.out, "{}", n).unwrap(),
-            Content::I32(n) => write!(self.out, "{}", n).unwrap(),
-            Content::I64(n) => write!(self.out, "{}", n).unwrap(),
-            Content::I128(n) => write!(self.out, "{}", n).unwrap(),
-            Content::F32(f) => {
-                if f.is_finite() {
-                    self.write_str(&format_float(f));
-                } else {
-                    self.write_str("null")
+        // containers are serialized with an explicit stack of pending work
+        // rather than recursively so deeply nested content can't overflow
+        // the stack.
+        let mut stack = vec![Task::Value(value)];
+
+        while let Some(task) = stack.pop() {
+            let value = match task {
+                Task::Value(value) => value,
+                Task::Seq(mut items, first) => {
+                    if let Some(item) = items.next() {
+                        self.write_comma(first);
+                        stack.push(Task::Seq(items, false));
+                        stack.push(Task::Value(item));
+                    }
+                    continue;
                 }
-            }
-            Content::F64(f) => {
-                if f.is_finite() {
-                    self.write_str(&format_float(f));
-                } else {
-                    self.write_str("null")
+                Task::Fields(mut fields, first) => {
+                    if let Some((key, value)) = fields.next() {
+                        self.write_comma(first);
+                        self.write_escaped_str(key);
+                        self.write_colon();
+                        stack.push(Task::Fields(fields, false));
+                        stack.push(Task::Value(value));
+                    }
+                    continue;
                 }
-            }
-            Content::Char(c) => self.write_escaped_str(&(*c).to_string()),
-            Content::String(s) => self.write_escaped_str(s),
-            Content::Bytes(bytes) => {
-                self.start_container('[');
-                for (idx, byte) in bytes.iter().enumerate() {
-                    self.write_comma(idx == 0);
-                    self.write_str(&byte.to_string());
+                Task::Map(mut entries, first) => {
+                    if let Some((key, value)) = entries.next() {
+                        self.write_comma(first);
+                        self.write_map_key(key);
+                        self.write_colon();
+                        stack.push(Task::Map(entries, false));
+                        stack.push(Task::Value(value));
+                    }
+                    continue;
                 }
-                self.end_container(']', bytes.is_empty());
-            }
-            Content::None | Content::Unit | Content::UnitStruct(_) => self.write_str("null"),
-            Content::Some(content) => self.serialize(content),
-            Content::UnitVariant(_, _, variant) => self.write_escaped_str(variant),
-            Content::NewtypeStruct(_, content) => self.serialize(content),
-            Content::NewtypeVariant(_, _, variant, content) => {
-                self.start_container('{');
-                self.write_comma(true);
-                self.write_escaped_str(variant);
-                self.write_colon();
-                self.serialize(content);
-                self.end_container('}', false);
-            }
-            Content::Seq(seq) | Content::Tuple(seq) | Content::TupleStruct(_, seq) => {
-                self.serialize_array(seq);
-            }
-            Content::TupleVariant(_, _, variant, seq) => {
-                self.start_container('{');
-                self.write_comma(true);
-                self.write_escaped_str(variant);
-                self.write_colon();
-                self.serialize_array(seq);
-                self.end_container('}', false);
-            }
-            Content::Map(map) => {
-                self.start_container('{');
-                for (idx, (key, value)) in map.iter().enumerate() {
-                    self.write_comma(idx == 0);
-                    let real_key = key.resolve_inner();
-                    if let Content::String(ref s) = real_key {
-                        self.write_escaped_str(s);
-                    } else if let Some(num) = real_key.as_i64() {
-                        self.write_escaped_str(&num.to_string());
-                    } else if let Some(num) = real_key.as_i128() {
-                        self.write_escaped_str(&num.to_string());
+                Task::End(c, empty) => {
+                    self.end_container(c, empty);
+                    continue;
+                }
+            };
+
+            match value {
+                Content::Bool(true) => self.write_str("true"),
+                Content::Bool(false) => self.write_str("false"),
+                Content::U8(n) => write!(self.out, "{}", n).unwrap(),
+                Content::U16(n) => write!(self.out, "{}", n).unwrap(),
+                Content::U32(n) => write!(self.out, "{}", n).unwrap(),
+                Content::U64(n) => write!(self.out, "{}", n).unwrap(),
+                Content::U128(n) => write!(self.out, "{}", n).unwrap(),
+                Content::I8(n) => write!(self.out, "{}", n).unwrap(),
+                Content::I16(n) => write!(self.out, "{}", n).unwrap(),
+                Content::I32(n) => write!(self.out, "{}", n).unwrap(),
+                Content::I64(n) => write!(self.out, "{}", n).unwrap(),
+                Content::I128(n) => write!(self.out, "{}", n).unwrap(),
+                Content::F32(f) => {
+                    if f.is_finite() {
+                        self.write_str(&format_float(f));
                     } else {
-                        panic!("cannot serialize maps without string keys to JSON");
+                        self.write_str("null")
                     }
-                    self.write_colon();
-                    self.serialize(value);
                 }
-                self.end_container('}', map.is_empty());
-            }
-            Content::Struct(_, fields) => {
-                self.serialize_object(fields);
-            }
-            Content::StructVariant(_, _, variant, fields) => {
-                self.start_container('{');
-                self.write_comma(true);
-                self.write_escaped_str(variant);
-                self.write_colon();
-                self.serialize_object(fields);
-                self.end_container('}', false);
+                Content::F64(f) => {
+                    if f.is_finite() {
+                        self.write_str(&format_float(f));
+                    } else {
+                        self.write_str("null")
+                    }
+                }
+                Content::Char(c) => self.write_escaped_str(&(*c).to_string()),
+                Content::String(s) => self.write_escaped_str(s),
+                Content::Bytes(bytes) => {
+                    self.start_container('[');
+                    for (idx, byte) in bytes.iter().enumerate() {
+                        self.write_comma(idx == 0);
+                        self.write_str(&byte.to_string());
+                    }
+                    self.end_container(']', bytes.is_empty());
+                }
+                Content::None | Content::Unit | Content::UnitStruct(_) => self.write_str("null"),
+                Content::Some(content) => stack.push(Task::Value(content)),
+                Content::UnitVariant(_, _, variant) => self.write_escaped_str(variant),
+                Content::NewtypeStruct(_, content) => stack.push(Task::Value(content)),
+                Content::NewtypeVariant(_, _, variant, content) => {
+                    self.start_variant(&mut stack, variant);
+                    stack.push(Task::Value(content));
+                }
+                Content::Seq(seq) | Content::Tuple(seq) | Content::TupleStruct(_, seq) => {
+                    self.start_array(&mut stack, seq);
+                }
+                Content::TupleVariant(_, _, variant, seq) => {
+                    self.start_variant(&mut stack, variant);
+                    self.start_array(&mut stack, seq);
+                }
+                Content::Map(map) => {
+                    self.start_container('{');
+                    stack.push(Task::End('}', map.is_empty()));
+                    stack.push(Task::Map(map.iter(), true));
+                }
+                Content::Struct(_, fields) => {
+                    self.start_object(&mut stack, fields);
+                }
+                Content::StructVariant(_, _, variant, fields) => {
+                    self.start_variant(&mut stack, variant);
+                    self.start_object(&mut stack, fields);
+                }
             }
         }
     }
@@ -15,7 +15,7 @@ pub mod yaml;
 #[cfg(feature = "serde")]
 pub use serialization::*;
 
-use std::fmt;
+use std::{fmt, mem};
 
 /// An internal error type for content related errors.
 #[derive(Debug)]
@@ -170,6 +170,37 @@ impl<'a> From<&'a [u8]> for Content {
     }
 }
 
+impl Drop for Content {
+    fn drop(&mut self) {
+        // dropping nested content recursively can overflow the stack, so the
+        // children are moved out and dropped from a stack on the heap instead.
+        fn take_children(content: &mut Content, stack: &mut Vec<Content>) {
+            match content {
+                Content::Some(inner)
+                | Content::NewtypeStruct(_, inner)
+                | Content::NewtypeVariant(_, _, _, inner) => {
+                    stack.push(mem::replace(&mut **inner, Content::None))
+                }
+                Content::Seq(seq)
+                | Content::Tuple(seq)
+                | Content::TupleStruct(_, seq)
+                | Content::TupleVariant(_, _, _, seq) => stack.append(seq),
+                Content::Map(map) => stack.extend(map.drain(..).flat_map(|(k, v)| [k, v])),
+                Content::Struct(_, fields) | Content::StructVariant(_, _, _, fields) => {
+                    stack.extend(fields.drain(..).map(|x| x.1))
+                }
+                _ => {}
+            }
+        }
+
+        let mut stack = Vec::new();
+        take_children(self, &mut stack);
+        while let Some(mut content) = stack.pop() {
+            take_children(&mut content, &mut stack);
+        }
+    }
+}
+
 impl Content {
     /// This resolves the innermost content in a chain of
     /// wrapped content.
@@ -346,59 +377,40 @@ impl Content {
 
     /// Recursively walks the content structure mutably.
     ///
-    /// The callback is invoked for every content in the tree.
+    /// The callback is invoked for every content in the tree.  If it returns
+    /// `false` the children of that content are skipped.
     pub fn walk<F: FnMut(&mut Content) -> bool>(&mut self, visit: &mut F) {
-        if !visit(self) {
-            return;
-        }
-
-        match *self {
-            Content::Some(ref mut inner) => {
-                Self::walk(&mut *inner, visit);
+        // an explicit stack so deeply nested content can't overflow the stack
+        let mut stack = vec![self];
+        while let Some(content) = stack.pop() {
+            if !visit(content) {
+                continue;
             }
-            Content::NewtypeStruct(_, ref mut inner) => {
-                Self::walk(&mut *inner, visit);
-            }
-            Content::NewtypeVariant(_, _, _, ref mut inner) => {
-                Self::walk(&mut *inner, visit);
-            }
-            Content::Seq(ref mut vec) => {
-                for inner in vec.iter_mut() {
-                    Self::walk(inner, visit);
-                }
-            }
-            Content::Map(ref mut vec) => {
-                for inner in vec.iter_mut() {
-                    Self::walk(&mut inner.0, visit);
-                    Self::walk(&mut inner.1, visit);
-                }
-            }
-            Content::Struct(_, ref mut vec) => {
-                for inner in vec.iter_mut() {
-                    Self::walk(&mut inner.1, visit);
-                }
-            }
-            Content::StructVariant(_, _, _, ref mut vec) => {
-                for inner in vec.iter_mut() {
-                    Self::walk(&mut inner.1, visit);
+
+            // children are pushed in reverse so that they are visited in order
+            match *content {
+                Content::Some(ref mut inner)
+                | Content::NewtypeStruct(_, ref mut inner)
+                | Content::NewtypeVariant(_, _, _, ref mut inner) => {
+                    stack.push(inner);
                 }
-            }
-            Content::Tuple(ref mut vec) => {
-                for inner in vec.iter_mut() {
-                    Self::walk(inner, visit);
+                Content::Seq(ref mut vec)
+                | Content::Tuple(ref mut vec)
+                | Content::TupleStruct(_, ref mut vec)
+                | Content::TupleVariant(_, _, _, ref mut vec) => {
+                    stack.extend(vec.iter_mut().rev());
                 }
-            }
-            Content::TupleStruct(_, ref mut vec) => {
-                for inner in vec.iter_mut() {
-                    Self::walk(inner, visit);
+                Content::Map(ref mut vec) => {
+                    for (key, value) in vec.iter_mut().rev() {
+                        stack.push(value);
+                        stack.push(key);
+                    }
                 }
-            }
-            Content::TupleVariant(_, _, _, ref mut vec) => {
-                for inner in vec.iter_mut() {
-                    Self::walk(inner, visit);
+                Content::Struct(_, ref mut vec) | Content::StructVariant(_, _, _, ref mut vec) => {
+                    stack.extend(vec.iter_mut().rev().map(|x| &mut x.1));
                 }
+                _ => {}
             }
-            _ => {}
         }
     }
 }
@@ -1,10 +1,14 @@
 pub mod vendored;
 
+use std::collections::hash_map::{Entry, HashMap};
+use std::fmt;
 use std::path::Path;
+use std::{slice, vec};
 
 use crate::content::{Content, Error};
 
-use crate::content::yaml::vendored::{yaml::Hash as YamlObj, Yaml as YamlValue};
+use crate::content::yaml::vendored::emitter::{escape_str, need_quotes};
+use crate::content::yaml::vendored::Yaml as YamlValue;
 
 pub fn parse_str(s: &str, filename: &Path) -> Result<Content, Error> {
     let mut blobs = crate::content::yaml::vendored::yaml::YamlLoader::load_from_str(s)
@@ -45,91 +49,397 @@ fn from_yaml_blob(blob: YamlValue, filename: &Path) -> Result<Content, Error> {
 }
 
 pub fn to_string(content: &Content) -> String {
-    let yaml_blob = to_yaml_value(content);
+    let mut buf = String::from("---\n");
+    to_writer(content, &mut buf).unwrap();
+    buf
+}
+
+/// Renders the content as YAML straight into the writer.
+///
+/// Unlike [`to_string`] this does not emit the leading document start
+/// marker.  The content is walked directly so no intermediate YAML tree
+/// is built.
+pub fn to_writer(content: &Content, writer: &mut dyn fmt::Write) -> fmt::Result {
+    let mut emitter = ContentEmitter { writer, level: -1 };
+    emitter.emit_node(Node::Content(content))?;
+    writeln!(emitter.writer)
+}
 
-    let mut buf = String::new();
-    let mut emitter = crate::content::yaml::vendored::emitter::YamlEmitter::new(&mut buf);
-    emitter.dump(&yaml_blob).unwrap();
+/// A borrowed view of something that renders as a YAML node.
+///
+/// Enum variants are rendered as single entry hashes keyed by the variant
+/// name, which is why the variant payloads show up here on their own.
+#[derive(Clone, Copy)]
+enum Node<'a> {
+    Content(&'a Content),
+    Str(&'a str),
+    Byte(u8),
+    Seq(&'a [Content]),
+    Fields(&'a [(&'static str, Content)]),
+}
+
+enum Kind<'a> {
+    Scalar,
+    Array(Items<'a>),
+    Hash(Entries<'a>),
+}
 
-    if !buf.ends_with('\n') {
-        buf.push('\n');
+enum Items<'a> {
+    Contents(slice::Iter<'a, Content>),
+    Bytes(slice::Iter<'a, u8>),
+}
+
+enum Entries<'a> {
+    Map(slice::Iter<'a, (Content, Content)>),
+    /// The entries of a map with duplicate keys, see
+    /// [`Entries::merge_duplicate_keys`].
+    Merged(vec::IntoIter<(&'a Content, &'a Content)>),
+    Fields(slice::Iter<'a, (&'static str, Content)>),
+    Variant(Option<(&'static str, Node<'a>)>),
+}
+
+impl<'a> Node<'a> {
+    /// Skips over wrappers that are transparent in YAML.
+    fn resolve(self) -> Node<'a> {
+        let mut node = self;
+        while let Node::Content(Content::Some(inner) | Content::NewtypeStruct(_, inner)) = node {
+            node = Node::Content(inner);
+        }
+        node
+    }
+
+    fn kind(self) -> Kind<'a> {
+        match self.resolve() {
+            Node::Seq(seq) => Kind::Array(Items::Contents(seq.iter())),
+            Node::Fields(fields) => Kind::Hash(Entries::Fields(fields.iter())),
+            Node::Str(_) | Node::Byte(_) => Kind::Scalar,
+            Node::Content(content) => match content {
+                Content::Bytes(bytes) => Kind::Array(Items::Bytes(bytes.iter())),
+                Content::Seq(seq) | Content::Tuple(seq) | Content::TupleStruct(_, seq) => {
+                    Kind::Array(Items::Contents(seq.iter()))
+                }
+                Content::Map(map) => Kind::Hash(Entries::Map(map.iter())),
+                Content::Struct(_, fields) => Kind::Hash(Entries::Fields(fields.iter())),
+                Content::NewtypeVariant(_, _, variant, inner) => {
+                    Kind::Hash(Entries::Variant(Some((variant, Node::Content(inner)))))
+                }
+                Content::TupleVariant(_, _, variant, seq) => {
+                    Kind::Hash(Entries::Variant(Some((variant, Node::Seq(seq)))))
+                }
+                Content::StructVariant(_, _, variant, fields) => {
+                    Kind::Hash(Entries::Variant(Some((variant, Node::Fields(fields)))))
+                }
+                _ => Kind::Scalar,
+            },
+        }
+    }
+
+    fn is_complex(self) -> bool {
+        !matches!(self.kind(), Kind::Scalar)
+    }
+
+    fn write_scalar(self, writer: &mut dyn fmt::Write) -> fmt::Result {
+        fn write_str(writer: &mut dyn fmt::Write, s: &str) -> fmt::Result {
+            if need_quotes(s) {
+                escape_str(writer, s)
+            } else {
+                writer.write_str(s)
+            }
+        }
+
+        let content = match self.resolve() {
+            Node::Content(content) => content,
+            Node::Str(s) => return write_str(writer, s),
+            Node::Byte(b) => return write!(writer, "{}", b),
+            Node::Seq(_) | Node::Fields(_) => unreachable!(),
+        };
+        match content {
+            Content::Bool(b) => writer.write_str(if *b { "true" } else { "false" }),
+            Content::U8(n) => write!(writer, "{}", n),
+            Content::U16(n) => write!(writer, "{}", n),
+            Content::U32(n) => write!(writer, "{}", n),
+            Content::U64(n) => write!(writer, "{}", n),
+            Content::U128(n) => write!(writer, "{}", n),
+            Content::I8(n) => write!(writer, "{}", n),
+            Content::I16(n) => write!(writer, "{}", n),
+            Content::I32(n) => write!(writer, "{}", n),
+            Content::I64(n) => write!(writer, "{}", n),
+            Content::I128(n) => write!(writer, "{}", n),
+            Content::F32(f) => write!(writer, "{}", f),
+            Content::F64(f) => write!(writer, "{}", f),
+            Content::Char(c) => write_str(writer, c.encode_utf8(&mut [0; 4])),
+            Content::String(s) => write_str(writer, s),
+            Content::UnitVariant(_, _, variant) => write_str(writer, variant),
+            Content::None | Content::Unit | Content::UnitStruct(_) => writer.write_str("~"),
+            _ => unreachable!(),
+        }
     }
-    buf
 }
 
-fn to_yaml_value(content: &Content) -> YamlValue {
-    fn translate_seq(seq: &[Content]) -> YamlValue {
-        let seq = seq.iter().map(to_yaml_value).collect();
-        YamlValue::Array(seq)
+impl Items<'_> {
+    fn is_empty(&self) -> bool {
+        match self {
+            Items::Contents(iter) => iter.len() == 0,
+            Items::Bytes(iter) => iter.len() == 0,
+        }
     }
+}
+
+impl<'a> Iterator for Items<'a> {
+    type Item = Node<'a>;
 
-    fn translate_fields(fields: &[(&str, Content)]) -> YamlValue {
-        let fields = fields
-            .iter()
-            .map(|(k, v)| (YamlValue::String(k.to_string()), to_yaml_value(v)))
-            .collect();
-        YamlValue::Hash(fields)
+    fn next(&mut self) -> Option<Node<'a>> {
+        match self {
+            Items::Contents(iter) => iter.next().map(Node::Content),
+            Items::Bytes(iter) => iter.next().map(|b| Node::Byte(*b)),
+        }
     }
+}
 
-    match content {
-        Content::Bool(b) => YamlValue::Boolean(*b),
-        Content::U8(n) => YamlValue::Integer(i64::from(*n)),
-        Content::U16(n) => YamlValue::Integer(i64::from(*n)),
-        Content::U32(n) => YamlValue::Integer(i64::from(*n)),
-        Content::U64(n) => YamlValue::Real(n.to_string()),
-        Content::U128(n) => YamlValue::Real(n.to_string()),
-        Content::I8(n) => YamlValue::Integer(i64::from(*n)),
-        Content::I16(n) => YamlValue::Integer(i64::from(*n)),
-        Content::I32(n) => YamlValue::Integer(i64::from(*n)),
-        Content::I64(n) => YamlValue::Integer(*n),
-        Content::I128(n) => YamlValue::Real(n.to_string()),
-        Content::F32(f) => YamlValue::Real(f.to_string()),
-        Content::F64(f) => YamlValue::Real(f.to_string()),
-        Content::Char(c) => YamlValue::String(c.to_string()),
-        Content::String(s) => YamlValue::String(s.to_owned()),
-        Content::Bytes(bytes) => {
-            let bytes = bytes
-                .iter()
-                .map(|b| YamlValue::Integer(i64::from(*b)))
-                .collect();
-            YamlValue::Array(bytes)
+impl<'a> Entries<'a> {
+    /// Merges map entries whose keys render the same, such as keys that were
+    /// all replaced by one redaction.
+    ///
+    /// Duplicate keys are not valid YAML, so like a map the first key keeps
+    /// its position and the last value wins.
+    fn merge_duplicate_keys(self) -> Entries<'a> {
+        let map = match self {
+            Entries::Map(ref iter) if iter.len() > 1 => iter.as_slice(),
+            entries => return entries,
+        };
+        let mut positions = HashMap::<String, usize>::new();
+        let mut merged: Vec<(&Content, &Content)> = Vec::with_capacity(map.len());
+        for (key, value) in map {
+            let mut rendered = String::new();
+            if to_writer(key, &mut rendered).is_err() {
+                return self;
+            }
+            match positions.entry(rendered) {
+                Entry::Occupied(entry) => merged[*entry.get()].1 = value,
+                Entry::Vacant(entry) => {
+                    entry.insert(merged.len());
+                    merged.push((key, value));
+                }
+            }
         }
-        Content::None | Content::Unit | Content::UnitStruct(_) => YamlValue::Null,
-        Content::Some(content) => to_yaml_value(content),
-        Content::UnitVariant(_, _, variant) => YamlValue::String(variant.to_string()),
-        Content::NewtypeStruct(_, content) => to_yaml_value(content),
-        Content::NewtypeVariant(_, _, variant, content) => {
-            let mut obj = YamlObj::new();
-            obj.insert(
-                YamlValue::String(variant.to_string()),
-                to_yaml_value(content),
-            );
-            YamlValue::Hash(obj)
+        if merged.len() == map.len() {
+            self
+        } else {
+            Entries::Merged(merged.into_iter())
         }
-        Content::Seq(seq) => translate_seq(seq),
-        Content::Tuple(seq) => translate_seq(seq),
-        Content::TupleStruct(_, seq) => translate_seq(seq),
-        Content::TupleVariant(_, _, variant, seq) => {
-            let mut obj = YamlObj::new();
-            obj.insert(YamlValue::String(variant.to_string()), translate_seq(seq));
-            YamlValue::Hash(obj)
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            Entries::Map(iter) => iter.len() == 0,
+            Entries::Merged(iter) => iter.len() == 0,
+            Entries::Fields(iter) => iter.len() == 0,
+            Entries::Variant(entry) => entry.is_none(),
         }
-        Content::Map(map) => {
-            let map = map
-                .iter()
-                .map(|(k, v)| (to_yaml_value(k), to_yaml_value(v)))
-                .collect();
+    }
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = (Node<'a>, Node<'a>);
 
-            YamlValue::Hash(map)
+    fn next(&mut self) -> Option<(Node<'a>, Node<'a>)> {
+        match self {
+            Entries::Map(iter) => iter
+                .next()
+                .map(|(k, v)| (Node::Content(k), Node::Content(v))),
+            Entries::Merged(iter) => iter
+                .next()
+                .map(|(k, v)| (Node::Content(k), Node::Content(v))),
+            Entries::Fields(iter) => iter.next().map(|(k, v)| (Node::Str(k), Node::Content(v))),
+            Entries::Variant(entry) => entry.take().map(|(k, v)| (Node::Str(k), v)),
         }
-        Content::Struct(_name, fields) => translate_fields(fields),
-        Content::StructVariant(_, _, variant, fields) => {
-            let mut obj = YamlObj::new();
-            obj.insert(
-                YamlValue::String(variant.to_string()),
-                translate_fields(fields),
-            );
-            YamlValue::Hash(obj)
+    }
+}
+
+/// Port of the vendored [`YamlEmitter`](vendored::emitter::YamlEmitter)
+/// which works on [`Content`] rather than on a YAML tree.
+///
+/// Instead of recursing, the emitter keeps a stack of pending tasks so that
+/// deeply nested content cannot overflow the stack.
+struct ContentEmitter<'w> {
+    writer: &'w mut dyn fmt::Write,
+    level: isize,
+}
+
+enum Task<'a> {
+    /// Emits a node.
+    Node(Node<'a>),
+    /// Emits a node following a `:` or `-`.  Nested containers go on a new
+    /// line unless `inline` is set or they are empty.
+    Val {
+        inline: bool,
+        node: Node<'a>,
+    },
+    /// Emits the remaining items of an array.
+    Array(Items<'a>, usize),
+    /// Emits the remaining entries of a hash.
+    Hash(Entries<'a>, usize),
+    /// Emits the value after a complex key.
+    ComplexVal(Node<'a>),
+    Str(&'static str),
+}
+
+impl ContentEmitter<'_> {
+    fn write_indent(&mut self) -> fmt::Result {
+        for _ in 0..self.level.max(0) {
+            self.writer.write_str("  ")?;
+        }
+        Ok(())
+    }
+
+    fn emit_node(&mut self, node: Node<'_>) -> fmt::Result {
+        let mut stack = vec![Task::Node(node)];
+
+        while let Some(task) = stack.pop() {
+            match task {
+                Task::Node(node) => match node.kind() {
+                    Kind::Array(items) if items.is_empty() => self.writer.write_str("[]")?,
+                    Kind::Array(items) => {
+                        self.level += 1;
+                        stack.push(Task::Array(items, 0));
+                    }
+                    Kind::Hash(entries) if entries.is_empty() => self.writer.write_str("{}")?,
+                    Kind::Hash(entries) => {
+                        self.level += 1;
+                        stack.push(Task::Hash(entries.merge_duplicate_keys(), 0));
+                    }
+                    Kind::Scalar => node.write_scalar(self.writer)?,
+                },
+                Task::Val { inline, node } => {
+                    let is_empty = match node.kind() {
+                        Kind::Array(items) => items.is_empty(),
+                        Kind::Hash(entries) => entries.is_empty(),
+                        Kind::Scalar => true,
+                    };
+                    if inline || is_empty {
+                        self.writer.write_str(" ")?;
+                    } else {
+                        writeln!(self.writer)?;
+                        self.level += 1;
+                        self.write_indent()?;
+                        self.level -= 1;
+                    }
+                    stack.push(Task::Node(node));
+                }
+                Task::Array(mut items, cnt) => match items.next() {
+                    Some(item) => {
+                        if cnt > 0 {
+                            writeln!(self.writer)?;
+                            self.write_indent()?;
+                        }
+                        self.writer.write_str("-")?;
+                        stack.push(Task::Array(items, cnt + 1));
+                        stack.push(Task::Val {
+                            inline: true,
+                            node: item,
+                        });
+                    }
+                    None => self.level -= 1,
+                },
+                Task::Hash(mut entries, cnt) => match entries.next() {
+                    Some((k, v)) => {
+                        if cnt > 0 {
+                            writeln!(self.writer)?;
+                            self.write_indent()?;
+                        }
+                        stack.push(Task::Hash(entries, cnt + 1));
+                        if k.is_complex() {
+                            self.writer.write_str("?")?;
+                            stack.push(Task::ComplexVal(v));
+                            stack.push(Task::Val {
+                                inline: true,
+                                node: k,
+                            });
+                        } else {
+                            stack.push(Task::Val {
+                                inline: false,
+                                node: v,
+                            });
+                            stack.push(Task::Str(":"));
+                            stack.push(Task::Node(k));
+                        }
+                    }
+                    None => self.level -= 1,
+                },
+                Task::ComplexVal(v) => {
+                    writeln!(self.writer)?;
+                    self.write_indent()?;
+                    self.writer.write_str(":")?;
+                    stack.push(Task::Val {
+                        inline: true,
+                        node: v,
+                    });
+                }
+                Task::Str(s) => self.writer.write_str(s)?,
+            }
         }
+
+        Ok(())
     }
 }
+
+#[test]
+fn test_to_string_enums_and_complex_keys() {
+    let content = Content::Map(vec![
+        (Content::from("unit"), Content::UnitVariant("E", 0, "A")),
+        (
+            Content::from("newtype"),
+            Content::NewtypeVariant("E", 1, "B", Box::new(Content::from(42u32))),
+        ),
+        (
+            Content::from("tuple"),
+            Content::TupleVariant("E", 2, "C", vec![Content::from(true), Content::None]),
+        ),
+        (
+            Content::from("struct"),
+            Content::StructVariant("E", 3, "D", vec![("x", Content::from("1"))]),
+        ),
+        (
+            Content::Seq(vec![Content::from(1u8), Content::from(2u8)]),
+            Content::Bytes(vec![]),
+        ),
+        (
+            Content::from("wrapped"),
+            Content::Some(Box::new(Content::NewtypeStruct(
+                "W",
+                Box::new(Content::Seq(vec![])),
+            ))),
+        ),
+    ]);
+
+    assert_eq!(
+        to_string(&content),
+        r#"---
+unit: A
+newtype:
+  B: 42
+tuple:
+  C:
+    - true
+    - ~
+struct:
+  D:
+    x: "1"
+? - 1
+  - 2
+: []
+wrapped: []
+"#
+    );
+}
+
+#[test]
+fn test_to_string_duplicate_keys() {
+    let content = Content::Map(vec![
+        (Content::from("[k]"), Content::from(1u32)),
+        (Content::from("b"), Content::from(2u32)),
+        (Content::from("[k]"), Content::from(3u32)),
+    ]);
+
+    assert_eq!(to_string(&content), "---\n\"[k]\": 3\nb: 2\n");
+}
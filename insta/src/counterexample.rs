@@ -0,0 +1,33 @@
+use proptest::strategy::Strategy;
+use proptest::test_runner::{Config, TestCaseResult, TestError, TestRunner};
+
+/// Runs a proptest property and renders the minimized counterexample.
+///
+/// Returns `no counterexample` if the property holds for all generated
+/// cases.  If it fails, the failing input is shrunk and returned together
+/// with the failure reported by the test:
+///
+/// ```text
+/// input: (
+///     0,
+///     -1,
+/// )
+/// failure: assertion failed: a <= a + b
+/// ```
+///
+/// The test runs with `config`, so its failure persistence applies as usual:
+/// seeds of earlier failures are replayed first, and a new failure is
+/// persisted next to `config.source_file`.
+pub fn run_proptest<S, F>(config: Config, strategy: &S, test: F) -> String
+where
+    S: Strategy,
+    F: Fn(S::Value) -> TestCaseResult,
+{
+    match TestRunner::new(config).run(strategy, test) {
+        Ok(()) => "no counterexample".to_string(),
+        Err(TestError::Fail(reason, value)) => {
+            format!("input: {:#?}\nfailure: {}", value, reason.message())
+        }
+        Err(TestError::Abort(reason)) => panic!("proptest aborted: {}", reason.message()),
+    }
+}
@@ -0,0 +1,415 @@
+//! Redactions for [`Debug`] snapshots.
+//!
+//! Debug output is only text, so it's parsed back into a tree of values
+//! first.  This relies on the layout of the pretty-printed (`{:#?}`) format,
+//! where every field and element sits on its own line, indented by four
+//! spaces per level.  Selectors match the same paths as for serialized
+//! values: struct fields and map keys by name, sequence and tuple elements by
+//! index, and the contents of newtypes such as `Some(..)` are skipped.
+use std::fmt;
+use std::ops::Range;
+
+use crate::content::Content;
+use crate::redaction::{check_redaction_matched, PathSegment, Redaction, Selector};
+use crate::settings::Settings;
+
+/// Formats a value with `{:#?}` and applies the redactions to the output.
+///
+/// The redactions of the current settings are applied after the given ones.
+/// If the output does not have the expected layout (for instance because of
+/// a custom [`Debug`] impl spanning several lines), it's returned unchanged.
+pub fn debug_value_redacted<T: fmt::Debug + ?Sized>(
+    value: &T,
+    redactions: &[(Selector, Redaction)],
+) -> String {
+    let mut rv = format!("{:#?}", value);
+    for (selector, redaction) in redactions {
+        rv = redact_debug(&rv, selector, redaction);
+    }
+    Settings::with(|settings| {
+        for (selector, redaction) in settings.iter_redactions() {
+            rv = redact_debug(&rv, selector, redaction);
+        }
+    });
+    rv
+}
+
+fn redact_debug(text: &str, selector: &Selector, redaction: &Redaction) -> String {
+    let mut parser = Parser {
+        text,
+        lines: text
+            .split('\n')
+            .scan(0, |offset, line| {
+                let start = *offset;
+                *offset += line.len() + 1;
+                Some(start..start + line.len())
+            })
+            .collect(),
+        line: 0,
+    };
+    let root = match parser.parse_value(0) {
+        Some(root) if parser.line == parser.lines.len() => root,
+        _ => {
+            check_redaction_matched(selector, false);
+            return text.to_string();
+        }
+    };
+
+    let mut replacements = vec![];
+    root.redact(text, selector, redaction, &mut vec![], &mut replacements);
+    check_redaction_matched(selector, !replacements.is_empty());
+    let mut rv = String::new();
+    let mut last = 0;
+    for (span, replacement) in replacements {
+        rv.push_str(&text[last..span.start]);
+        rv.push_str(&replacement);
+        last = span.end;
+    }
+    rv.push_str(&text[last..]);
+    rv
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// `Name { field: value }`, `{ key: value }` or `{ value }`.
+    Braces,
+    /// `[value]`
+    Brackets,
+    /// `Name(value)` or `(value)`.
+    Parens,
+    /// A tuple struct with a single field, most notably `Some(value)`.
+    Newtype,
+}
+
+/// The entries of a container, labelled with the field name or key for
+/// structs and maps.
+type Entries = Vec<(Option<Content>, Node)>;
+
+/// A value in the debug output.
+struct Node {
+    /// Where the value is in the text, without the trailing comma.
+    span: Range<usize>,
+    /// The kind of container and its entries.
+    children: Option<(Kind, Entries)>,
+    /// The name of a struct, tuple struct or enum variant.
+    name: String,
+}
+
+impl Node {
+    fn redact<'a>(
+        &'a self,
+        text: &str,
+        selector: &Selector,
+        redaction: &Redaction,
+        path: &mut Vec<PathSegment<'a>>,
+        replacements: &mut Vec<(Range<usize>, String)>,
+    ) {
+        if selector.is_match(path) {
+            let mut value = self.to_content(text);
+            redaction.redact(&mut value, path);
+            let line_start = text[..self.span.start].rfind('\n').map_or(0, |x| x + 1);
+            let indent =
+                text[line_start..].len() - text[line_start..].trim_start_matches(' ').len();
+            let rendered = format!("{:#?}", DebugContent(&value));
+            replacements.push((
+                self.span.clone(),
+                rendered.replace('\n', &format!("\n{:indent$}", "", indent = indent)),
+            ));
+            return;
+        }
+
+        if let Some((kind, ref entries)) = self.children {
+            let len = entries.len() as u64;
+            for (idx, (label, child)) in entries.iter().enumerate() {
+                let depth = path.len();
+                match (kind, label) {
+                    (Kind::Newtype, _) => {}
+                    (_, Some(label)) => path.push(PathSegment::Key(label)),
+                    (_, None) => path.push(PathSegment::Index(idx as u64, len)),
+                }
+                child.redact(text, selector, redaction, path, replacements);
+                path.truncate(depth);
+            }
+        }
+    }
+
+    /// Converts the value into content for dynamic redactions.
+    fn to_content(&self, text: &str) -> Content {
+        let (kind, entries) = match self.children {
+            Some((kind, ref entries)) => (kind, entries),
+            None => return atom_to_content(&text[self.span.clone()]),
+        };
+        match kind {
+            Kind::Newtype if self.name == "Some" => {
+                Content::Some(Box::new(entries[0].1.to_content(text)))
+            }
+            Kind::Newtype => entries[0].1.to_content(text),
+            Kind::Braces if entries.iter().all(|x| x.0.is_some()) => Content::Map(
+                entries
+                    .iter()
+                    .map(|(label, node)| (label.clone().unwrap(), node.to_content(text)))
+                    .collect(),
+            ),
+            _ => Content::Seq(entries.iter().map(|x| x.1.to_content(text)).collect()),
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    lines: Vec<Range<usize>>,
+    line: usize,
+}
+
+impl Parser<'_> {
+    /// Parses the value starting at `start` on the current line, and moves on
+    /// to the line after it.
+    fn parse_value(&mut self, start: usize) -> Option<Node> {
+        let line = self.lines.get(self.line)?.clone();
+        let rest = &self.text[start..line.end];
+        let value = rest.strip_suffix(',').unwrap_or(rest);
+        self.line += 1;
+
+        let (kind, closer) = match value.chars().last() {
+            Some('{') => (Kind::Braces, '}'),
+            Some('[') => (Kind::Brackets, ']'),
+            Some('(') => (Kind::Parens, ')'),
+            _ => {
+                return Some(Node {
+                    span: start..start + value.len(),
+                    children: None,
+                    name: String::new(),
+                })
+            }
+        };
+        let name = value[..value.len() - 1].trim_end().to_string();
+        let indent = indent_of(&self.text[line]);
+
+        let mut entries = vec![];
+        loop {
+            let line = self.lines.get(self.line)?.clone();
+            let s = &self.text[line.clone()];
+            let entry_indent = indent_of(s);
+            if entry_indent == indent && s[indent..].starts_with(closer) {
+                let end = line.start + indent + 1;
+                self.line += 1;
+                let kind = match kind {
+                    Kind::Parens if entries.len() == 1 && !name.is_empty() => Kind::Newtype,
+                    kind => kind,
+                };
+                return Some(Node {
+                    span: start..end,
+                    children: Some((kind, entries)),
+                    name,
+                });
+            }
+            if entry_indent != indent + 4 {
+                return None;
+            }
+            let entry_start = line.start + entry_indent;
+            let (label, value_start) = match kind {
+                Kind::Braces => match split_label(&self.text[entry_start..line.end]) {
+                    Some((label, offset)) => (Some(label), entry_start + offset),
+                    None => (None, entry_start),
+                },
+                _ => (None, entry_start),
+            };
+            entries.push((label, self.parse_value(value_start)?));
+        }
+    }
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// Splits the field name or key off a struct or map entry.  Returns the label
+/// and the offset of the value.
+fn split_label(entry: &str) -> Option<(Content, usize)> {
+    let key_len = if let Some(quoted) = entry.strip_prefix('"') {
+        let mut escaped = false;
+        quoted
+            .char_indices()
+            .find(|&(_, c)| {
+                let end = !escaped && c == '"';
+                escaped = !escaped && c == '\\';
+                end
+            })
+            .map(|x| x.0 + 2)?
+    } else {
+        entry.find(": ")?
+    };
+    if !entry[key_len..].starts_with(": ") {
+        return None;
+    }
+    let key = &entry[..key_len];
+    let label = match atom_to_content(key) {
+        // struct fields are plain identifiers
+        Content::String(_) if !key.starts_with('"') => Content::String(key.to_string()),
+        content => content,
+    };
+    Some((label, key_len + 2))
+}
+
+fn atom_to_content(s: &str) -> Content {
+    match s {
+        "true" => return Content::Bool(true),
+        "false" => return Content::Bool(false),
+        "None" => return Content::None,
+        "()" => return Content::Unit,
+        _ => {}
+    }
+    if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+        if let Some(s) = unescape(&s[1..s.len() - 1]) {
+            return Content::String(s);
+        }
+    } else if s.len() >= 2 && s.starts_with('\'') && s.ends_with('\'') {
+        let s = unescape(&s[1..s.len() - 1]);
+        let mut chars = s.iter().flat_map(|x| x.chars());
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Content::Char(c);
+        }
+    } else if let Ok(value) = s.parse() {
+        return Content::U64(value);
+    } else if let Ok(value) = s.parse() {
+        return Content::I64(value);
+    } else if s.contains('.') {
+        if let Ok(value) = s.parse() {
+            return Content::F64(value);
+        }
+    }
+    Content::String(s.to_string())
+}
+
+/// Reverses the escaping of strings and chars in debug output.
+fn unescape(s: &str) -> Option<String> {
+    let mut rv = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            rv.push(c);
+            continue;
+        }
+        rv.push(match chars.next()? {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '0' => '\0',
+            'u' => {
+                let hex = chars.as_str().strip_prefix('{')?.split_once('}')?.0;
+                let c = char::from_u32(u32::from_str_radix(hex, 16).ok()?)?;
+                chars = chars.as_str()[hex.len() + 2..].chars();
+                c
+            }
+            c => c,
+        });
+    }
+    Some(rv)
+}
+
+/// Formats content like the [`Debug`] impl of the original value would.
+struct DebugContent<'a>(&'a Content);
+
+impl fmt::Debug for DebugContent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self.0 {
+            Content::Bool(v) => fmt::Debug::fmt(&v, f),
+            Content::U8(v) => fmt::Debug::fmt(&v, f),
+            Content::U16(v) => fmt::Debug::fmt(&v, f),
+            Content::U32(v) => fmt::Debug::fmt(&v, f),
+            Content::U64(v) => fmt::Debug::fmt(&v, f),
+            Content::U128(v) => fmt::Debug::fmt(&v, f),
+            Content::I8(v) => fmt::Debug::fmt(&v, f),
+            Content::I16(v) => fmt::Debug::fmt(&v, f),
+            Content::I32(v) => fmt::Debug::fmt(&v, f),
+            Content::I64(v) => fmt::Debug::fmt(&v, f),
+            Content::I128(v) => fmt::Debug::fmt(&v, f),
+            Content::F32(v) => fmt::Debug::fmt(&v, f),
+            Content::F64(v) => fmt::Debug::fmt(&v, f),
+            Content::Char(v) => fmt::Debug::fmt(&v, f),
+            Content::String(ref v) => fmt::Debug::fmt(v, f),
+            Content::Bytes(ref v) => fmt::Debug::fmt(v, f),
+            Content::None => f.write_str("None"),
+            Content::Some(ref v) => f.debug_tuple("Some").field(&DebugContent(v)).finish(),
+            Content::Unit => f.write_str("()"),
+            Content::UnitStruct(name) | Content::UnitVariant(_, _, name) => f.write_str(name),
+            Content::NewtypeStruct(name, ref v) | Content::NewtypeVariant(_, _, name, ref v) => {
+                f.debug_tuple(name).field(&DebugContent(v)).finish()
+            }
+            Content::Seq(ref v) => f.debug_list().entries(v.iter().map(DebugContent)).finish(),
+            Content::Tuple(ref v) => debug_tuple(f, "", v),
+            Content::TupleStruct(name, ref v) | Content::TupleVariant(_, _, name, ref v) => {
+                debug_tuple(f, name, v)
+            }
+            Content::Map(ref v) => f
+                .debug_map()
+                .entries(v.iter().map(|(k, v)| (DebugContent(k), DebugContent(v))))
+                .finish(),
+            Content::Struct(name, ref fields) | Content::StructVariant(_, _, name, ref fields) => {
+                let mut s = f.debug_struct(name);
+                for (key, value) in fields {
+                    s.field(key, &DebugContent(value));
+                }
+                s.finish()
+            }
+        }
+    }
+}
+
+fn debug_tuple(f: &mut fmt::Formatter<'_>, name: &str, values: &[Content]) -> fmt::Result {
+    let mut t = f.debug_tuple(name);
+    for value in values {
+        t.field(&DebugContent(value));
+    }
+    t.finish()
+}
+
+#[test]
+fn test_redact_debug() {
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    struct Event {
+        id: u32,
+        at: Option<String>,
+        tags: Vec<&'static str>,
+        meta: std::collections::BTreeMap<&'static str, (u8, char)>,
+    }
+
+    let event = Event {
+        id: 42,
+        at: Some("2024-01-01T00:00:00Z".into()),
+        tags: vec!["a", "b\"c"],
+        meta: [("x", (1, 'y'))].into_iter().collect(),
+    };
+    let redactions = vec![
+        (Selector::parse(".at").unwrap(), Redaction::from("[date]")),
+        (
+            Selector::parse(".tags[1]").unwrap(),
+            crate::dynamic_redaction(|value, path| {
+                assert_eq!(path.to_string(), ".tags.1");
+                assert_eq!(value.as_str(), Some("b\"c"));
+                Content::Seq(vec![Content::from(1u8), Content::from(2u8)])
+            }),
+        ),
+        (Selector::parse(".meta.x[0]").unwrap(), Redaction::from(0u8)),
+    ];
+    crate::assert_snapshot!(debug_value_redacted(&event, &redactions), @r###"
+    Event {
+        id: 42,
+        at: "[date]",
+        tags: [
+            "a",
+            [
+                1,
+                2,
+            ],
+        ],
+        meta: {
+            "x": (
+                0,
+                'y',
+            ),
+        },
+    }
+    "###);
+}
@@ -0,0 +1,147 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Compiles a Rust source file and returns the normalized compiler
+/// diagnostics.
+///
+/// The file is compiled on its own as a library with `rustc` (or the
+/// compiler in `RUSTC`), so it can only use the standard library.  Nothing
+/// is linked and no output is kept.  Diagnostics are rendered without colors
+/// and normalized so they stay stable across machines and compiler versions:
+///
+/// - the directory of the file is replaced with `$DIR` and paths into the
+///   standard library with `$RUST`
+/// - column numbers are removed from locations, and line numbers as well for
+///   paths into the standard library
+/// - the `aborting due to ...` and `... warnings emitted` summaries are left
+///   out
+///
+/// An empty string is returned if the file compiles without warnings.  This
+/// is what [`assert_diagnostics_snapshot!`](crate::assert_diagnostics_snapshot!)
+/// uses.
+pub fn rustc_diagnostics<P: AsRef<Path>>(path: P) -> String {
+    static OUT_DIR_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let path = path.as_ref();
+    let path = env::current_dir()
+        .map(|x| x.join(path))
+        .unwrap_or_else(|_| path.to_path_buf());
+    assert!(path.is_file(), "no such source file: {}", path.display());
+    let out_dir = env::temp_dir().join(format!(
+        "insta-rustc-{}-{}",
+        std::process::id(),
+        OUT_DIR_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let output = Command::new(env::var_os("RUSTC").unwrap_or_else(|| "rustc".into()))
+        .arg(&path)
+        .args(["--edition=2021", "--crate-type=lib", "--emit=metadata"])
+        .args(["--color=never", "--crate-name=insta_diagnostics"])
+        .arg("--out-dir")
+        .arg(&out_dir)
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run rustc: {}", err));
+    fs::remove_dir_all(&out_dir).ok();
+
+    let dir = path
+        .parent()
+        .map(|x| x.display().to_string())
+        .unwrap_or_default();
+    normalize_diagnostics(&String::from_utf8_lossy(&output.stderr), &dir)
+}
+
+fn normalize_diagnostics(stderr: &str, dir: &str) -> String {
+    let mut rv = String::new();
+    for line in stderr.lines() {
+        if line.starts_with("error: aborting due to")
+            || (line.starts_with("warning: ") && line.ends_with(" emitted"))
+        {
+            continue;
+        }
+        // leaving out the summaries can leave empty lines behind
+        if line.is_empty() && (rv.is_empty() || rv.ends_with("\n\n")) {
+            continue;
+        }
+        rv.push_str(&normalize_line(line, dir));
+        rv.push('\n');
+    }
+    rv.trim_end().to_string()
+}
+
+/// Normalizes the location lines (`--> file:line:col` and
+/// `::: file:line:col`) of a diagnostic.
+fn normalize_line(line: &str, dir: &str) -> String {
+    let (indent, marker, location) = match ["--> ", "::: "]
+        .iter()
+        .find_map(|marker| line.split_once(marker).map(|x| (x.0, *marker, x.1)))
+    {
+        Some((indent, marker, location)) if indent.trim().is_empty() => (indent, marker, location),
+        _ => return line.to_string(),
+    };
+
+    let mut location = location.replace('\\', "/");
+    let dir = dir.replace('\\', "/");
+    let mut keep_line = true;
+    if let Some(rest) = location.strip_prefix(&dir).filter(|_| !dir.is_empty()) {
+        location = format!("$DIR{}", rest);
+    } else if let Some(idx) = location
+        .find("/rustlib/src/rust/library/")
+        .map(|x| x + "/rustlib/src/rust/library/".len())
+        .or_else(|| {
+            location
+                .strip_prefix("/rustc/")
+                .and_then(|x| x.find("/library/"))
+                .map(|x| "/rustc/".len() + x + "/library/".len())
+        })
+    {
+        location = format!("$RUST/{}", &location[idx..]);
+        keep_line = false;
+    }
+
+    // strip the column (and the line in the standard library), which drift
+    // between compiler versions.
+    let mut parts = location.rsplitn(3, ':').collect::<Vec<_>>();
+    if parts.len() == 3 && parts[..2].iter().all(|x| x.parse::<u32>().is_ok()) {
+        parts.remove(0);
+        if !keep_line {
+            parts.remove(0);
+        }
+        parts.reverse();
+        location = parts.join(":");
+    }
+    format!("{}{}{}", indent, marker, location)
+}
+
+#[test]
+fn test_normalize_diagnostics() {
+    let stderr = r#"error[E0308]: mismatched types
+ --> /src/tests/ui/mismatch.rs:2:18
+  |
+2 |     let x: u32 = "a";
+  |            ---   ^^^ expected `u32`, found `&str`
+  |
+ ::: /home/u/.rustup/toolchains/stable/lib/rustlib/src/rust/library/core/src/option.rs:572:1
+ ::: /rustc/0123abc/library/alloc/src/vec/mod.rs:10:5
+
+error: aborting due to 1 previous error
+warning: 2 warnings emitted
+
+For more information about this error, try `rustc --explain E0308`.
+"#;
+    assert_eq!(
+        normalize_diagnostics(stderr, "/src/tests/ui"),
+        r#"error[E0308]: mismatched types
+ --> $DIR/mismatch.rs:2
+  |
+2 |     let x: u32 = "a";
+  |            ---   ^^^ expected `u32`, found `&str`
+  |
+ ::: $RUST/core/src/option.rs
+ ::: $RUST/alloc/src/vec/mod.rs
+
+For more information about this error, try `rustc --explain E0308`."#
+    );
+}
@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use similar::{Algorithm, TextDiff};
+
+pub use similar::DiffOp;
+
+/// Computes the differences between two snapshots.
+///
+/// Snapshots are compared line by line.  The engine returns the operations
+/// that turn the old lines into the new lines, and insta renders them in the
+/// failure output of assertions and in test reports.
+///
+/// The built-in engines are the algorithms of [`DiffAlgorithm`], which can
+/// also be selected for `cargo insta review`.  A custom engine can be set with
+/// [`Settings::set_diff_engine`], for instance one that understands the
+/// structure of the snapshot:
+///
+/// ```
+/// use insta::{DiffEngine, DiffOp, Settings};
+/// use std::sync::Arc;
+///
+/// /// Reports all lines as replaced.
+/// struct ReplaceAll;
+///
+/// impl DiffEngine for ReplaceAll {
+///     fn diff_lines(&self, old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+///         vec![DiffOp::Replace {
+///             old_index: 0,
+///             old_len: old.len(),
+///             new_index: 0,
+///             new_len: new.len(),
+///         }]
+///     }
+/// }
+///
+/// let mut settings = Settings::clone_current();
+/// settings.set_diff_engine(Arc::new(ReplaceAll));
+/// ```
+///
+/// [`Settings::set_diff_engine`]: crate::Settings::set_diff_engine
+pub trait DiffEngine: Send + Sync {
+    /// Diffs the lines `old` against the lines `new`.
+    ///
+    /// Lines include their line endings.  The returned operations must cover
+    /// both inputs in order, just like [`similar::capture_diff_slices`] does.
+    fn diff_lines(&self, old: &[&str], new: &[&str]) -> Vec<DiffOp>;
+}
+
+/// The built-in diff algorithms.
+///
+/// The default is [`DiffAlgorithm::Patience`].  Without a custom engine the
+/// algorithm is picked with the `behavior.diff_algorithm` config option or
+/// the `INSTA_DIFF_ALGORITHM` environment variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffAlgorithm {
+    /// Patience diff, which keeps unique lines aligned.
+    Patience,
+    /// The classic Myers diff.
+    Myers,
+    /// Diffing by the longest common subsequence.
+    Lcs,
+}
+
+impl Default for DiffAlgorithm {
+    fn default() -> DiffAlgorithm {
+        DiffAlgorithm::Patience
+    }
+}
+
+impl DiffEngine for DiffAlgorithm {
+    fn diff_lines(&self, old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+        let algorithm = match self {
+            DiffAlgorithm::Patience => Algorithm::Patience,
+            DiffAlgorithm::Myers => Algorithm::Myers,
+            DiffAlgorithm::Lcs => Algorithm::Lcs,
+        };
+        TextDiff::configure()
+            .algorithm(algorithm)
+            .timeout(Duration::from_millis(500))
+            .diff_slices(old, new)
+            .ops()
+            .to_vec()
+    }
+}
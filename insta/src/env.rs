@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::io::Write;
 use std::path::{Path, PathBuf};
@@ -6,7 +7,8 @@ use std::{env, fmt, fs};
 
 use crate::utils::is_ci;
 use crate::{
-    content::{yaml, Content},
+    content::{json, yaml, Content},
+    diff::DiffAlgorithm,
     elog,
 };
 
@@ -85,14 +87,30 @@ pub enum UnreferencedSnapshots {
     Ignore,
 }
 
-/// Snapshot update flag
+/// Controls how snapshots are written when assertions run.
+///
+/// This is normally picked with the `INSTA_UPDATE` environment variable or
+/// the `behavior.update` config option, but can also be set for a block of
+/// tests with [`Settings::set_snapshot_update`](crate::Settings::set_snapshot_update).
+/// The settings only provide a default: an explicitly set `INSTA_UPDATE`, an
+/// update mode of `no`, and running on CI take precedence over them.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum SnapshotUpdate {
+    /// Writes snapshots for failing assertions into `.snap` files, bypassing
+    /// review.
     Always,
+    /// [`SnapshotUpdate::No`] on CI and [`SnapshotUpdate::New`] otherwise.
     Auto,
+    /// Writes previously unseen snapshots in place and puts changes to
+    /// existing snapshots up for review.
     Unseen,
+    /// Writes snapshots for failing assertions into `.snap.new` files,
+    /// pending review.
     New,
+    /// Does not write snapshots at all.
     No,
+    /// Writes all snapshots, even if the assertions pass.
     Force,
 }
 
@@ -128,8 +146,16 @@ impl std::error::Error for Error {
 pub struct ToolConfig {
     force_pass: bool,
     require_full_match: bool,
+    fsync: bool,
+    read_only: bool,
+    diff_algorithm: DiffAlgorithm,
+    diff_context: Option<usize>,
+    path_prefixes: Vec<(String, String)>,
     output: OutputBehavior,
     snapshot_update: SnapshotUpdate,
+    snapshot_update_from_env: bool,
+    snapshot_path: Option<PathBuf>,
+    prepend_module_to_snapshot: Option<bool>,
     #[cfg(feature = "glob")]
     glob_fail_fast: bool,
     #[cfg(feature = "_cargo_insta_internal")]
@@ -224,6 +250,72 @@ impl ToolConfig {
                 Ok("1") => true,
                 _ => return Err(Error::Env("INSTA_FORCE_PASS")),
             },
+            fsync: match env::var("INSTA_FSYNC").as_deref() {
+                Err(_) | Ok("") => resolve(&cfg, &["behavior", "fsync"])
+                    .and_then(|x| x.as_bool())
+                    .unwrap_or(false),
+                Ok("0") => false,
+                Ok("1") => true,
+                _ => return Err(Error::Env("INSTA_FSYNC")),
+            },
+            read_only: match env::var("INSTA_READ_ONLY").as_deref() {
+                Err(_) | Ok("") => resolve(&cfg, &["behavior", "read_only"])
+                    .and_then(|x| x.as_bool())
+                    .unwrap_or_else(is_bazel_test),
+                Ok("0") => false,
+                Ok("1") => true,
+                _ => return Err(Error::Env("INSTA_READ_ONLY")),
+            },
+            diff_algorithm: {
+                let env_var = env::var("INSTA_DIFF_ALGORITHM");
+                let val = match env_var.as_deref() {
+                    Err(_) | Ok("") => resolve(&cfg, &["behavior", "diff_algorithm"])
+                        .and_then(|x| x.as_str())
+                        .unwrap_or("patience"),
+                    Ok(val) => val,
+                };
+                match val {
+                    "patience" => DiffAlgorithm::Patience,
+                    "myers" => DiffAlgorithm::Myers,
+                    "lcs" => DiffAlgorithm::Lcs,
+                    _ => return Err(Error::Env("INSTA_DIFF_ALGORITHM")),
+                }
+            },
+            diff_context: match env::var("INSTA_DIFF_CONTEXT").as_deref() {
+                Err(_) | Ok("") => match resolve(&cfg, &["behavior", "diff_context"]) {
+                    None => Some(4),
+                    Some(x) if x.as_str() == Some("full") => None,
+                    Some(x) => Some(
+                        x.as_u64()
+                            .ok_or(Error::Config("diff_context"))?
+                            .try_into()
+                            .map_err(|_| Error::Config("diff_context"))?,
+                    ),
+                },
+                Ok("full") => None,
+                Ok(val) => Some(val.parse().map_err(|_| Error::Env("INSTA_DIFF_CONTEXT"))?),
+            },
+            path_prefixes: match resolve(&cfg, &["behavior", "path_prefixes"])
+                .map(|x| x.resolve_inner())
+            {
+                None => Vec::new(),
+                Some(Content::Map(map)) => {
+                    let mut rv = Vec::new();
+                    for (prefix, replacement) in map {
+                        match (prefix.as_str(), replacement.as_str()) {
+                            (Some(prefix), Some(replacement)) if !prefix.is_empty() => {
+                                rv.push((prefix.to_string(), replacement.to_string()))
+                            }
+                            _ => return Err(Error::Config("path_prefixes")),
+                        }
+                    }
+                    // longer prefixes first, so that a nested path wins over
+                    // the path containing it.
+                    rv.sort_by_key(|x| std::cmp::Reverse(x.0.len()));
+                    rv
+                }
+                Some(_) => return Err(Error::Config("path_prefixes")),
+            },
             output: {
                 let env_var = env::var("INSTA_OUTPUT");
                 let val = match env_var.as_deref() {
@@ -266,6 +358,24 @@ impl ToolConfig {
                     _ => return Err(Error::Env("INSTA_UPDATE")),
                 }
             },
+            snapshot_update_from_env: !matches!(
+                env::var("INSTA_UPDATE").as_deref(),
+                Err(_) | Ok("")
+            ),
+            snapshot_path: match resolve(&cfg, &["behavior", "snapshot_path"]) {
+                None => None,
+                Some(x) => Some(x.as_str().ok_or(Error::Config("snapshot_path"))?.into()),
+            },
+            prepend_module_to_snapshot: match resolve(
+                &cfg,
+                &["behavior", "prepend_module_to_snapshot"],
+            ) {
+                None => None,
+                Some(x) => Some(
+                    x.as_bool()
+                        .ok_or(Error::Config("prepend_module_to_snapshot"))?,
+                ),
+            },
             #[cfg(feature = "glob")]
             glob_fail_fast: match env::var("INSTA_GLOB_FAIL_FAST").as_deref() {
                 Err(_) | Ok("") => resolve(&cfg, &["behavior", "glob_fail_fast"])
@@ -339,6 +449,62 @@ impl ToolConfig {
         self.force_pass
     }
 
+    /// Should written snapshots be synced to disk before moving on?
+    pub fn fsync(&self) -> bool {
+        self.fsync
+    }
+
+    /// Should snapshot files be left alone, with writes only being reported?
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Returns the algorithm used to diff snapshots.
+    pub fn diff_algorithm(&self) -> DiffAlgorithm {
+        self.diff_algorithm
+    }
+
+    /// Returns the number of unchanged lines shown around changes in diffs,
+    /// or `None` to show the snapshots in full.
+    pub fn diff_context(&self) -> Option<usize> {
+        self.diff_context
+    }
+
+    /// Replaces the configured path prefixes in `s`.
+    ///
+    /// Prefixes only match whole path components, so `/build` leaves
+    /// `/buildroot` alone.  The replacements are made in a single pass and
+    /// are not themselves remapped again.
+    pub fn remap_path_prefixes<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        let mut rv = String::new();
+        let mut copied = 0;
+        let mut replaced = false;
+        let mut prev = None;
+        for (idx, c) in s.char_indices() {
+            if idx >= copied {
+                let rest = &s[idx..];
+                if let Some((prefix, replacement)) =
+                    self.path_prefixes.iter().find(|(prefix, _)| {
+                        rest.starts_with(prefix.as_str())
+                            && starts_path(prev, prefix)
+                            && ends_path(prefix, &rest[prefix.len()..])
+                    })
+                {
+                    rv.push_str(&s[copied..idx]);
+                    rv.push_str(replacement);
+                    copied = idx + prefix.len();
+                    replaced = true;
+                }
+            }
+            prev = Some(c);
+        }
+        if !replaced {
+            return Cow::Borrowed(s);
+        }
+        rv.push_str(&s[copied..]);
+        Cow::Owned(rv)
+    }
+
     /// Returns the intended output behavior for insta.
     pub fn output_behavior(&self) -> OutputBehavior {
         self.output
@@ -349,6 +515,35 @@ impl ToolConfig {
         self.snapshot_update
     }
 
+    /// Returns the snapshot update behavior, given the one set in the
+    /// settings.
+    ///
+    /// The settings win over the config file, but not over an explicitly set
+    /// `INSTA_UPDATE`, over `no`, or on CI, so that CI can always refuse to
+    /// write snapshots.
+    pub fn snapshot_update_with(&self, from_settings: Option<SnapshotUpdate>) -> SnapshotUpdate {
+        match from_settings {
+            Some(update)
+                if !self.snapshot_update_from_env
+                    && self.snapshot_update != SnapshotUpdate::No
+                    && !is_ci() =>
+            {
+                update
+            }
+            _ => self.snapshot_update(),
+        }
+    }
+
+    /// Returns the configured default snapshot path.
+    pub fn snapshot_path(&self) -> Option<&Path> {
+        self.snapshot_path.as_deref()
+    }
+
+    /// Returns whether snapshot file names start with the module by default.
+    pub fn prepend_module_to_snapshot(&self) -> Option<bool> {
+        self.prepend_module_to_snapshot
+    }
+
     /// Returns whether the glob should fail fast, as snapshot failures within the glob macro will appear only at the end of execution unless `glob_fail_fast` is set.
     #[cfg(feature = "glob")]
     pub fn glob_fail_fast(&self) -> bool {
@@ -407,8 +602,8 @@ pub enum SnapshotUpdateBehavior {
 }
 
 /// Returns the intended snapshot update behavior.
-pub fn snapshot_update_behavior(tool_config: &ToolConfig, unseen: bool) -> SnapshotUpdateBehavior {
-    match tool_config.snapshot_update() {
+pub fn snapshot_update_behavior(update: SnapshotUpdate, unseen: bool) -> SnapshotUpdateBehavior {
+    match update {
         SnapshotUpdate::Always => SnapshotUpdateBehavior::InPlace,
         SnapshotUpdate::Auto => {
             if is_ci() {
@@ -430,6 +625,35 @@ pub fn snapshot_update_behavior(tool_config: &ToolConfig, unseen: bool) -> Snaps
     }
 }
 
+fn is_separator(c: char) -> bool {
+    c == '/' || c == '\\'
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+/// Checks that a path prefix found after `prev` starts a path rather than
+/// continuing another one.
+fn starts_path(prev: Option<char>, prefix: &str) -> bool {
+    match prev {
+        None => true,
+        Some(c) if is_separator(c) => prefix.starts_with(is_separator),
+        Some(c) => !is_name_char(c),
+    }
+}
+
+/// Checks that a path prefix followed by `rest` ends at a path component.
+fn ends_path(prefix: &str, rest: &str) -> bool {
+    prefix.ends_with(is_separator) || !rest.starts_with(is_name_char)
+}
+
+/// Checks if we are running under `bazel test`, where the sources are a
+/// read-only runfiles tree.
+fn is_bazel_test() -> bool {
+    env::var_os("TEST_SRCDIR").is_some() && env::var_os("BUILD_WORKSPACE_DIRECTORY").is_none()
+}
+
 /// Returns the cargo workspace path for a crate manifest, like
 /// `/Users/janedoe/projects/insta` when passed
 /// `/Users/janedoe/projects/insta/insta/Cargo.toml`.
@@ -442,6 +666,15 @@ pub fn get_cargo_workspace(manifest_dir: &str) -> Arc<PathBuf> {
         return PathBuf::from(workspace_root).into();
     }
 
+    // Bazel does not run tests through cargo.  `bazel run` points us at the
+    // source tree, `bazel test` only has the (read-only) runfiles tree.
+    if let Ok(workspace_root) = env::var("BUILD_WORKSPACE_DIRECTORY") {
+        return PathBuf::from(workspace_root).into();
+    }
+    if let (Ok(srcdir), Ok(workspace)) = (env::var("TEST_SRCDIR"), env::var("TEST_WORKSPACE")) {
+        return PathBuf::from(srcdir).join(workspace).into();
+    }
+
     let error_message = || {
         format!(
             "`cargo metadata --format-version=1 --no-deps` in path `{}`",
@@ -455,13 +688,21 @@ pub fn get_cargo_workspace(manifest_dir: &str) -> Arc<PathBuf> {
         .unwrap()
         .entry(manifest_dir.to_string())
         .or_insert_with(|| {
-            let output = std::process::Command::new(
+            let output = match std::process::Command::new(
                 env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()),
             )
             .args(["metadata", "--format-version=1", "--no-deps"])
             .current_dir(manifest_dir)
             .output()
-            .unwrap_or_else(|e| panic!("failed to run {}\n\n{}", error_message(), e));
+            {
+                Ok(output) => output,
+                // targets such as wasm cannot spawn processes at all, so the
+                // best guess is the crate itself.
+                Err(e) if e.kind() == std::io::ErrorKind::Unsupported => {
+                    return PathBuf::from(manifest_dir).into();
+                }
+                Err(e) => panic!("failed to run {}\n\n{}", error_message(), e),
+            };
 
             crate::content::yaml::vendored::yaml::YamlLoader::load_from_str(
                 std::str::from_utf8(&output.stdout).unwrap(),
@@ -498,6 +739,62 @@ fn test_get_cargo_workspace() {
     assert!(workspace.ends_with("insta"));
 }
 
+#[test]
+fn test_path_prefixes() {
+    let dir = env::temp_dir().join(format!("insta-path-prefixes-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("insta.yaml"),
+        "behavior:\n  path_prefixes:\n    /build: \"[BUILD]\"\n    /build/workspace: \"[SRC]\"\n    /src: /build/src\n",
+    )
+    .unwrap();
+    let config = ToolConfig::from_workspace(&dir).unwrap();
+    // an empty prefix would match everywhere
+    fs::write(
+        dir.join("insta.yaml"),
+        "behavior:\n  path_prefixes:\n    \"\": \"[EMPTY]\"\n",
+    )
+    .unwrap();
+    assert!(matches!(
+        ToolConfig::from_workspace(&dir),
+        Err(Error::Config("path_prefixes"))
+    ));
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(
+        config.remap_path_prefixes("error in /build/workspace/src/lib.rs (see /build/log)"),
+        "error in [SRC]/src/lib.rs (see [BUILD]/log)"
+    );
+    assert_eq!(
+        config.remap_path_prefixes("/src/lib.rs:1 \"/build\" /build/workspace"),
+        "/build/src/lib.rs:1 \"[BUILD]\" [SRC]"
+    );
+    assert!(matches!(
+        config.remap_path_prefixes("nothing to see"),
+        Cow::Borrowed(_)
+    ));
+    assert!(matches!(
+        config.remap_path_prefixes("/buildroot/src /tmp/build /tmp/src"),
+        Cow::Borrowed(_)
+    ));
+}
+
+#[test]
+fn test_snapshot_layout_config() {
+    let dir = env::temp_dir().join(format!("insta-snapshot-layout-{}", std::process::id()));
+    fs::create_dir_all(dir.join(".config")).unwrap();
+    fs::write(
+        dir.join(".config/insta.yaml"),
+        "behavior:\n  snapshot_path: __snapshots__\n  prepend_module_to_snapshot: false\n",
+    )
+    .unwrap();
+    let config = ToolConfig::from_workspace(&dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(config.snapshot_path(), Some(Path::new("__snapshots__")));
+    assert_eq!(config.prepend_module_to_snapshot(), Some(false));
+}
+
 #[cfg(feature = "_cargo_insta_internal")]
 impl std::str::FromStr for TestRunner {
     type Err = ();
@@ -541,6 +838,104 @@ pub fn memoize_snapshot_file(snapshot_file: &Path) {
     }
 }
 
+/// Appends the outcome of an assertion to the results file, as part of
+/// producing test reports.
+///
+/// The result is only built if a results file was requested.
+pub fn record_assertion_result<F: FnOnce() -> Content>(result: F) {
+    if let Ok(path) = env::var("INSTA_ASSERTION_RESULTS_FILE") {
+        let mut f = fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .unwrap();
+        // written in one go so lines from parallel tests do not interleave
+        f.write_all(format!("{}\n", json::to_string(&result())).as_bytes())
+            .unwrap();
+    }
+}
+
+/// An event about a pending snapshot, for editor integrations.
+///
+/// If `INSTA_EVENTS` is set, events are sent there as JSON lines.  It can
+/// name a file (events are appended), a Unix domain socket or a TCP listener
+/// (`tcp://127.0.0.1:9000`).  Editors can use this to show pending snapshots
+/// as they come up rather than watching the file system.  The events are:
+///
+/// - `created`: a pending snapshot was written (`.snap.new` or `.pending-snap`)
+/// - `updated`: a snapshot file was written directly (`INSTA_UPDATE=always`)
+/// - `accepted`, `rejected`: `cargo insta` accepted or rejected a pending
+///   snapshot
+#[derive(Debug, Clone, Copy)]
+pub struct SnapshotEvent<'a> {
+    /// What happened: `created`, `updated`, `accepted` or `rejected`.
+    pub event: &'a str,
+    pub snapshot_name: Option<&'a str>,
+    /// The `.snap` file, unset for inline snapshots.
+    pub snapshot_file: Option<&'a Path>,
+    /// The `.snap.new` or `.pending-snap` file.
+    pub pending_file: Option<&'a Path>,
+    /// The Rust file with the assertion.
+    pub source_file: Option<&'a Path>,
+    /// The line of the assertion.
+    pub line: Option<u32>,
+}
+
+impl SnapshotEvent<'_> {
+    /// Sends the event if a listener was requested.
+    ///
+    /// Listeners are optional, so failures to reach them are ignored.
+    pub fn emit(&self) {
+        let target = match env::var("INSTA_EVENTS") {
+            Ok(target) if !target.is_empty() => target,
+            _ => return,
+        };
+        let path = |p: Option<&Path>| p.map_or(Content::None, |x| x.display().to_string().into());
+        let event = Content::Map(vec![
+            (Content::from("event"), Content::from(self.event)),
+            (
+                Content::from("kind"),
+                Content::from(if self.snapshot_file.is_some() {
+                    "file"
+                } else {
+                    "inline"
+                }),
+            ),
+            (
+                Content::from("snapshot_name"),
+                self.snapshot_name.map_or(Content::None, Content::from),
+            ),
+            (Content::from("snapshot_file"), path(self.snapshot_file)),
+            (Content::from("pending_file"), path(self.pending_file)),
+            (Content::from("source_file"), path(self.source_file)),
+            (
+                Content::from("line"),
+                self.line.map_or(Content::None, Content::from),
+            ),
+        ]);
+        send_event(&target, format!("{}\n", json::to_string(&event)).as_bytes()).ok();
+    }
+}
+
+fn send_event(target: &str, line: &[u8]) -> std::io::Result<()> {
+    if let Some(addr) = target.strip_prefix("tcp://") {
+        return std::net::TcpStream::connect(addr)?.write_all(line);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if fs::metadata(target).map_or(false, |x| x.file_type().is_socket()) {
+            return std::os::unix::net::UnixStream::connect(target)?.write_all(line);
+        }
+    }
+    // written in one go so lines from parallel tests do not interleave
+    fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(target)?
+        .write_all(line)
+}
+
 fn resolve<'a>(value: &'a Content, path: &[&str]) -> Option<&'a Content> {
     path.iter()
         .try_fold(value, |node, segment| match node.resolve_inner() {
@@ -98,7 +98,7 @@ pub fn glob_exec<F: FnMut(&Path)>(workspace_dir: &Path, base: &Path, pattern: &s
             path.file_name().unwrap()
         };
 
-        settings.set_snapshot_suffix(snapshot_suffix.to_str().unwrap());
+        settings.set_input_file_suffix(snapshot_suffix.to_str().unwrap());
         settings.bind(|| {
             f(path);
         });
@@ -130,7 +130,7 @@ pub fn glob_exec<F: FnMut(&Path)>(workspace_dir: &Path, base: &Path, pattern: &s
     }
 }
 
-fn find_common_prefix(sorted_paths: &[PathBuf]) -> Option<&Path> {
+pub(crate) fn find_common_prefix(sorted_paths: &[PathBuf]) -> Option<&Path> {
     let first = sorted_paths.first()?;
     let last = sorted_paths.last()?;
     let prefix_len = first
@@ -0,0 +1,69 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use globset::GlobBuilder;
+use libtest_mimic::{Arguments, Trial};
+use walkdir::WalkDir;
+
+use crate::glob::find_common_prefix;
+use crate::settings::Settings;
+
+/// Runs a closure for all files matching a glob, each as its own test.
+///
+/// This parses the test harness arguments from the command line, runs the
+/// tests and exits the process.
+pub fn harness_exec<F>(base: &Path, pattern: &str, f: F) -> !
+where
+    F: Fn(&Path) + Send + Sync + 'static,
+{
+    let args = Arguments::from_args();
+    let settings = Settings::clone_current();
+
+    let glob = GlobBuilder::new(pattern)
+        .case_insensitive(true)
+        .literal_separator(true)
+        .build()
+        .unwrap()
+        .compile_matcher();
+
+    let mut matching_files = vec![];
+    if !settings.allow_empty_glob() || base.exists() {
+        for file in WalkDir::new(base).follow_links(true) {
+            let path = file.unwrap().into_path();
+            if glob.is_match(path.strip_prefix(base).unwrap_or(&path)) {
+                matching_files.push(path);
+            }
+        }
+    }
+    if matching_files.is_empty() && !settings.allow_empty_glob() {
+        panic!("the harness! macro did not match any files.");
+    }
+
+    // the tests are named like the snapshot suffixes of glob!, and filtering
+    // with `cargo test <name>` matches on them.
+    matching_files.sort();
+    let common_prefix = find_common_prefix(&matching_files).map(|x| x.to_path_buf());
+    let f = Arc::new(f);
+    let trials = matching_files
+        .into_iter()
+        .map(|path| {
+            let name = match common_prefix {
+                Some(ref prefix) => path.strip_prefix(prefix).unwrap().as_os_str(),
+                None => path.file_name().unwrap(),
+            }
+            .to_str()
+            .unwrap()
+            .replace('\\', "/");
+            let mut settings = settings.clone();
+            settings.set_input_file(&path);
+            settings.set_input_file_suffix(name.as_str());
+            let f = f.clone();
+            Trial::test(name, move || {
+                settings.bind(|| f(&path));
+                Ok(())
+            })
+        })
+        .collect();
+
+    libtest_mimic::run(&args, trials).exit()
+}
@@ -0,0 +1,162 @@
+use std::fmt::Write;
+
+use http::header::{HeaderName, CONTENT_TYPE};
+use http::Response;
+
+/// Headers whose values change on every request.  Their values are replaced
+/// with the placeholder so snapshots stay stable.
+const REDACTED_HEADERS: &[(&str, &str)] = &[
+    ("content-length", "[content-length]"),
+    ("date", "[date]"),
+    ("set-cookie", "[set-cookie]"),
+    ("request-id", "[request-id]"),
+    ("x-request-id", "[request-id]"),
+    ("x-correlation-id", "[request-id]"),
+    ("x-amzn-requestid", "[request-id]"),
+];
+
+/// Renders an HTTP response for a snapshot.
+///
+/// The output starts with the status line, followed by the headers sorted by
+/// name, an empty line and the body:
+///
+/// ```text
+/// HTTP/1.1 200 OK
+/// content-length: [content-length]
+/// content-type: application/json
+/// date: [date]
+///
+/// {
+///   "id": 42
+/// }
+/// ```
+///
+/// The values of headers that change on every request (`Date`, `Set-Cookie`,
+/// `Content-Length` and common request id headers) are replaced with
+/// placeholders.  JSON bodies are pretty-printed, other bodies are included as
+/// text, and bodies that are not valid UTF-8 are summarized.
+///
+/// This is what [`assert_http_snapshot!`](crate::assert_http_snapshot!) uses.
+pub fn format_http_response<B: AsRef<[u8]>>(response: &Response<B>) -> String {
+    let mut rv = format!("{:?} {}\n", response.version(), response.status());
+
+    let mut names = response.headers().keys().collect::<Vec<_>>();
+    names.sort_by_key(|x| x.as_str());
+    for name in names {
+        for value in response.headers().get_all(name) {
+            let value = match redacted_value(name) {
+                Some(placeholder) => placeholder.to_string(),
+                None => String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            };
+            writeln!(rv, "{}: {}", name, value).unwrap();
+        }
+    }
+
+    let body = response.body().as_ref();
+    if !body.is_empty() {
+        rv.push('\n');
+        match std::str::from_utf8(body) {
+            Ok(text) if is_json(response) => rv.push_str(&pretty_json(text)),
+            Ok(text) => rv.push_str(text),
+            Err(_) => write!(rv, "<{} bytes of binary data>", body.len()).unwrap(),
+        }
+    }
+
+    rv
+}
+
+fn redacted_value(name: &HeaderName) -> Option<&'static str> {
+    REDACTED_HEADERS
+        .iter()
+        .find(|x| x.0 == name.as_str())
+        .map(|x| x.1)
+}
+
+fn is_json<B>(response: &Response<B>) -> bool {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.split(';').next())
+        .map_or(false, |x| {
+            let x = x.trim();
+            x.eq_ignore_ascii_case("application/json") || x.ends_with("+json")
+        })
+}
+
+/// Re-indents a JSON document.
+///
+/// This works on the tokens only, so numbers and strings are kept exactly as
+/// they were sent and invalid documents come out mostly unchanged.
+fn pretty_json(s: &str) -> String {
+    fn newline(rv: &mut String, indent: usize) {
+        rv.push('\n');
+        for _ in 0..indent {
+            rv.push_str("  ");
+        }
+    }
+
+    let mut rv = String::with_capacity(s.len() * 2);
+    let mut indent = 0;
+    let mut chars = s.trim().chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                rv.push(c);
+                while let Some(c) = chars.next() {
+                    rv.push(c);
+                    match c {
+                        '\\' => rv.extend(chars.next()),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '{' | '[' => {
+                rv.push(c);
+                while chars.peek().map_or(false, |x| x.is_whitespace()) {
+                    chars.next();
+                }
+                let close = if c == '{' { '}' } else { ']' };
+                if chars.peek() == Some(&close) {
+                    rv.push(close);
+                    chars.next();
+                } else {
+                    indent += 1;
+                    newline(&mut rv, indent);
+                }
+            }
+            '}' | ']' => {
+                indent = indent.saturating_sub(1);
+                newline(&mut rv, indent);
+                rv.push(c);
+            }
+            ',' => {
+                rv.push(c);
+                newline(&mut rv, indent);
+            }
+            ':' => rv.push_str(": "),
+            c if c.is_whitespace() => {}
+            c => rv.push(c),
+        }
+    }
+    rv
+}
+
+#[test]
+fn test_pretty_json() {
+    assert_eq!(
+        pretty_json(r#" {"a": [1, 2.50, {}], "b\"": "x, y: {z}", "c": {"d": []}} "#),
+        r#"{
+  "a": [
+    1,
+    2.50,
+    {}
+  ],
+  "b\"": "x, y: {z}",
+  "c": {
+    "d": []
+  }
+}"#
+    );
+}
@@ -114,6 +114,14 @@
 //! For macros that work with [`serde`] this crate also permits redacting of
 //! partial values.  See [redactions in the
 //! documentation](https://insta.rs/docs/redactions/) for more information.
+//! [`assert_debug_snapshot!`] accepts redactions as well, which are applied to
+//! the pretty-printed [`Debug`] output.
+//!
+//! The serialized value does not need a concrete type.  Trait objects that
+//! implement [`serde::Serialize`], such as `&dyn erased_serde::Serialize` or
+//! `Box<dyn erased_serde::Serialize>`, can be passed to these macros and to
+//! [`Settings::set_info`] directly, which helps plugin systems that only hold
+//! trait objects.
 //!
 //! # Updating snapshots
 //!
@@ -135,6 +143,9 @@
 //! - `no`: does not write to snapshot files at all; just runs tests
 //! - `force`: forcibly updates snapshot files, even if assertions pass
 //!
+//! The mode can also be set in code with [`Settings::set_snapshot_update`],
+//! which takes precedence over the environment variable.
+//!
 //! When `new`, `auto` or `unseen` is used, the
 //! [`cargo-insta`](https://crates.io/crates/cargo-insta) command can be used to
 //! review the snapshots conveniently:
@@ -149,6 +160,17 @@
 //! For more information [read the cargo insta
 //! docs](https://insta.rs/docs/cli/).
 //!
+//! Editors can be told about pending snapshots as they come up: if
+//! `INSTA_EVENTS` names a file, a Unix domain socket or a TCP address
+//! (`tcp://127.0.0.1:9000`), an event is sent there as a line of JSON whenever
+//! a pending snapshot is `created`, a snapshot is `updated` in place or
+//! `cargo insta` `accepted` or `rejected` a snapshot:
+//!
+//! ```text
+//! {"event":"created","kind":"inline","snapshot_name":"foo","snapshot_file":null,
+//!  "pending_file":"/project/src/.lib.rs.pending-snap","source_file":"/project/src/lib.rs","line":9}
+//! ```
+//!
 //! # Inline Snapshots
 //!
 //! Additionally snapshots can also be stored inline.  In that case the format
@@ -170,6 +192,23 @@
 //! review the proposed changes and update the source files on acceptance
 //! automatically.
 //!
+//! # Parameterized Tests
+//!
+//! Snapshots in tests generated by [`rstest`](https://crates.io/crates/rstest)
+//! (`#[case]`) and [`test-case`](https://crates.io/crates/test-case)
+//! (`#[test_case]`) get the case as snapshot suffix, so every case has its
+//! own snapshot:
+//!
+//! - `#[case::empty(...)]` and `#[test_case(... ; "empty")]` are stored as
+//!   `name@empty`.
+//! - unnamed `test-case` cases are named after their arguments, for instance
+//!   `name@1_expects_2` for `#[test_case(1 => 2)]`.
+//! - unnamed `rstest` cases are numbered — `name@case_1`, `name@case_2` — so
+//!   give cases a name if they might get reordered.
+//!
+//! This only happens for automatically named snapshots, and when no
+//! snapshot suffix is set.
+//!
 //! # Features
 //!
 //! The following features exist:
@@ -182,6 +221,11 @@
 //! * `redactions`: enables support for redactions
 //! * `filters`: enables support for filters
 //! * `glob`: enables support for globbing ([`glob!`])
+//! * `harness`: enables a custom test harness for file driven tests ([`harness!`])
+//! * `http`: enables snapshots of HTTP responses ([`assert_http_snapshot!`])
+//! * `proptest`: enables snapshots of proptest counterexamples ([`assert_proptest_snapshot!`])
+//! * `tracing`: enables snapshots of `tracing` events ([`assert_tracing_snapshot!`])
+//! * `log`: enables snapshots of `log` records ([`assert_log_snapshot!`])
 //! * `colors`: enables color output (enabled by default)
 //!
 //! For legacy reasons the `json` and `yaml` features are enabled by default in
@@ -201,6 +245,10 @@
 //! this optimization you can disable the default features and manually opt into
 //! what you want.
 //!
+//! The runtime itself does not depend on any procedural macros.  The parser
+//! for redaction selectors (and with it [`pest_derive`](https://docs.rs/pest_derive))
+//! is only compiled when the `redactions` feature is enabled.
+//!
 //! # Settings
 //!
 //! There are some settings that can be changed on a per-thread (and thus
@@ -223,6 +271,27 @@
 //!   update: "auto" | "new" | "always" | "no" | "unseen" | "force"
 //!   # also set by INSTA_GLOB_FAIL_FAST
 //!   glob_fail_fast: true/false
+//!   # sync written snapshot files to disk before continuing, which helps on
+//!   # machines with slow or unreliable disks.  also set by INSTA_FSYNC
+//!   fsync: true/false
+//!   # the algorithm used to diff snapshots, in assertions and in
+//!   # cargo insta review.  also set by INSTA_DIFF_ALGORITHM
+//!   diff_algorithm: "patience" | "myers" | "lcs"
+//!   # the number of unchanged lines shown around changes in diffs, or
+//!   # "full" to show the whole snapshot.  the default is 4.  also set by
+//!   # INSTA_DIFF_CONTEXT
+//!   diff_context: 4 | "full"
+//!   # replaces path prefixes in snapshot contents and metadata, so that
+//!   # snapshots taken in containers or on CI match the ones taken locally
+//!   path_prefixes:
+//!     /build/workspace: "[SRC]"
+//!   # never write snapshot files, only report what would be written.  this
+//!   # is the default under `bazel test`.  also set by INSTA_READ_ONLY
+//!   read_only: true/false
+//!   # the default for Settings::set_snapshot_path, relative to the test
+//!   snapshot_path: "snapshots"
+//!   # the default for Settings::set_prepend_module_to_snapshot
+//!   prepend_module_to_snapshot: true/false
 //!
 //! # these are used by cargo insta test
 //! test:
@@ -271,7 +340,10 @@
 
 #[macro_use]
 mod macros;
+mod cmd;
 mod content;
+mod diagnostics;
+mod diff;
 mod env;
 mod output;
 mod runtime;
@@ -279,22 +351,55 @@ mod runtime;
 mod serialization;
 mod settings;
 mod snapshot;
+mod store;
 mod utils;
 
 #[cfg(feature = "redactions")]
 mod redaction;
 
+#[cfg(feature = "redactions")]
+mod debug_redaction;
+
 #[cfg(feature = "filters")]
 mod filters;
 
 #[cfg(feature = "glob")]
 mod glob;
 
+#[cfg(feature = "harness")]
+mod harness;
+
+#[cfg(feature = "http")]
+mod http_response;
+
+#[cfg(feature = "proptest")]
+mod counterexample;
+
+#[cfg(any(feature = "tracing", feature = "log"))]
+mod capture;
+
 #[cfg(test)]
 mod test;
 
+pub use crate::cmd::Cmd;
+pub use crate::diagnostics::rustc_diagnostics;
+pub use crate::diff::{DiffAlgorithm, DiffEngine, DiffOp};
+pub use crate::env::SnapshotUpdate;
 pub use crate::settings::Settings;
-pub use crate::snapshot::{MetaData, Snapshot, TextSnapshotKind};
+pub use crate::snapshot::{MetaData, Snapshot, TextSnapshotKind, SNAPSHOT_FORMAT_VERSION};
+pub use crate::store::{FileSystemStore, MemoryStore, ReadOnlyStore, SnapshotStore};
+
+#[cfg(feature = "http")]
+pub use crate::http_response::format_http_response;
+
+#[cfg(feature = "proptest")]
+pub use crate::counterexample::run_proptest;
+
+#[cfg(feature = "tracing")]
+pub use crate::capture::capture_tracing;
+
+#[cfg(feature = "log")]
+pub use crate::capture::capture_log;
 
 /// Exposes some library internals.
 ///
@@ -307,7 +412,7 @@ pub mod internals {
     pub use crate::content::Content;
     #[cfg(feature = "filters")]
     pub use crate::filters::Filters;
-    pub use crate::runtime::AutoName;
+    pub use crate::runtime::{AutoName, SnapshotAssertion};
     pub use crate::settings::SettingsBindDropGuard;
     pub use crate::snapshot::{MetaData, SnapshotContents};
     #[cfg(feature = "redactions")]
@@ -324,8 +429,8 @@ pub mod _cargo_insta_support {
     pub use crate::{
         content::Error as ContentError,
         env::{
-            Error as ToolConfigError, OutputBehavior, SnapshotUpdate, TestRunner, ToolConfig,
-            UnreferencedSnapshots,
+            Error as ToolConfigError, OutputBehavior, SnapshotEvent, SnapshotUpdate, TestRunner,
+            ToolConfig, UnreferencedSnapshots,
         },
         output::SnapshotPrinter,
         snapshot::PendingInlineSnapshot,
@@ -356,8 +461,26 @@ pub mod _macro_support {
     #[cfg(feature = "glob")]
     pub use crate::glob::glob_exec;
 
+    #[cfg(feature = "harness")]
+    pub use crate::harness::harness_exec;
+
+    #[cfg(feature = "http")]
+    pub use crate::http_response::format_http_response;
+
+    #[cfg(feature = "proptest")]
+    pub use crate::counterexample::run_proptest;
+    #[cfg(feature = "proptest")]
+    pub use proptest::test_runner::Config as ProptestConfig;
+
+    #[cfg(feature = "tracing")]
+    pub use crate::capture::capture_tracing;
+
+    #[cfg(feature = "log")]
+    pub use crate::capture::capture_log;
+
     #[cfg(feature = "redactions")]
     pub use crate::{
-        redaction::Redaction, redaction::Selector, serialization::serialize_value_redacted,
+        debug_redaction::debug_value_redacted, redaction::Redaction, redaction::Selector,
+        serialization::serialize_value_redacted,
     };
 }
@@ -300,17 +300,71 @@ macro_rules! _prepare_snapshot_for_redaction {
 /// Asserts a [`Debug`] snapshot.
 ///
 /// The value needs to implement the [`Debug`] trait.  This is useful for
-/// simple values that do not implement the [`serde::Serialize`] trait, but does not
-/// permit redactions.
+/// simple values that do not implement the [`serde::Serialize`] trait.
 ///
 /// Debug is called with `"{:#?}"`, which means this uses pretty-print.
+///
+/// Redactions can be passed like for the serialization based macros, which
+/// also applies the redactions of the current settings.  They are applied to
+/// the pretty-printed output, so this only works for values whose [`Debug`]
+/// impls lay out their fields like `#[derive(Debug)]` does (requires the
+/// `redactions` feature):
+///
+#[cfg_attr(feature = "redactions", doc = " ```no_run")]
+#[cfg_attr(not(feature = "redactions"), doc = " ```ignore")]
+/// # #[derive(Debug)] struct Event { id: u32, timestamp: u64 }
+/// # let event = Event { id: 1, timestamp: 0 };
+/// insta::assert_debug_snapshot!(event, {
+///     ".timestamp" => "[timestamp]",
+/// });
+/// ```
 #[macro_export]
 macro_rules! assert_debug_snapshot {
+    ($value:expr, $(match ..)? {$($k:expr => $v:expr),* $(,)?} $($arg:tt)*) => {{
+        let transform = |value| {
+            $crate::_prepare_debug_snapshot_for_redaction!(value, {$($k => $v),*})
+        };
+        $crate::_assert_snapshot_base!(transform=transform, $value $($arg)*);
+    }};
+    ($name:expr, $value:expr, $(match ..)? {$($k:expr => $v:expr),* $(,)?} $(,)?) => {{
+        let transform = |value| {
+            $crate::_prepare_debug_snapshot_for_redaction!(value, {$($k => $v),*})
+        };
+        $crate::_assert_snapshot_base!(transform=transform, $name, $value);
+    }};
     ($($arg:tt)*) => {
         $crate::_assert_snapshot_base!(transform=|v| std::format!("{:#?}", v), $($arg)*)
     };
 }
 
+#[cfg(feature = "redactions")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _prepare_debug_snapshot_for_redaction {
+    ($value:expr, {$($k:expr => $v:expr),*}) => {
+        $crate::_macro_support::debug_value_redacted(
+            &$value,
+            &std::vec![
+                $((
+                    $crate::_macro_support::Selector::parse($k).unwrap(),
+                    $crate::_macro_support::Redaction::from($v)
+                ),)*
+            ],
+        )
+    }
+}
+
+#[cfg(not(feature = "redactions"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _prepare_debug_snapshot_for_redaction {
+    ($value:expr, {$($k:expr => $v:expr),*}) => {
+        compile_error!(
+            "insta was compiled without redactions support. Enable the `redactions` feature."
+        )
+    };
+}
+
 /// Asserts a [`Debug`] snapshot in compact format.
 ///
 /// The value needs to implement the [`Debug`] trait.  This is useful for
@@ -454,6 +508,238 @@ macro_rules! assert_snapshot {
     };
 }
 
+/// Creates a [`Cmd`](crate::Cmd) for a program and its arguments.
+///
+/// ```
+/// let cmd = insta::cmd!("git", "status", "--short");
+/// ```
+#[macro_export]
+macro_rules! cmd {
+    ($program:expr $(, $arg:expr)* $(,)?) => {
+        $crate::Cmd::new($program)$(.arg($arg))*
+    };
+}
+
+/// Asserts a snapshot of running an external command.
+///
+/// The value is a [`Cmd`](crate::Cmd) (or a [`std::process::Command`]).  The
+/// command is run and its exit code, stdout and stderr are snapshotted
+/// together.
+///
+/// ```no_run
+/// # use insta::*;
+/// assert_cmd_snapshot!(cmd!("echo", "hello"), @r###"
+/// success: true
+/// exit_code: 0
+/// ----- stdout -----
+/// hello
+/// ----- stderr -----
+/// "###);
+///
+/// // with the working directory replaced by `[CWD]`
+/// assert_cmd_snapshot!(cmd!("pwd").normalize_cwd());
+/// ```
+#[macro_export]
+macro_rules! assert_cmd_snapshot {
+    ($($arg:tt)*) => {
+        $crate::_assert_snapshot_base!(transform=|v| $crate::Cmd::from(v).run(), $($arg)*)
+    };
+}
+
+/// Asserts a snapshot of the compiler diagnostics for a source file.
+///
+/// The value is the path of a Rust source file, usually relative to the
+/// crate root.  The file is compiled on its own and the normalized
+/// diagnostics are snapshotted, which keeps the expected output of UI tests
+/// with the other snapshots; see [`rustc_diagnostics`](crate::rustc_diagnostics)
+/// for how the file is compiled and what is normalized.
+///
+/// ```no_run
+/// # use insta::*;
+/// assert_diagnostics_snapshot!("tests/ui/mismatched_types.rs", @r###"
+/// error[E0308]: mismatched types
+///  --> $DIR/mismatched_types.rs:2
+///   |
+/// 2 |     let x: u32 = "a";
+///   |            ---   ^^^ expected `u32`, found `&str`
+///   |            |
+///   |            expected due to this
+///
+/// For more information about this error, try `rustc --explain E0308`.
+/// "###);
+/// ```
+#[macro_export]
+macro_rules! assert_diagnostics_snapshot {
+    ($($arg:tt)*) => {
+        $crate::_assert_snapshot_base!(transform=$crate::rustc_diagnostics, $($arg)*)
+    };
+}
+
+/// Asserts an HTTP response snapshot.
+///
+/// The value needs to be an [`http::Response`] with a body that implements
+/// `AsRef<[u8]>` (for instance `String`, `Vec<u8>` or `Bytes`).  The status
+/// line, the sorted headers and the body are snapshotted.  Values of headers
+/// such as `Date` or `Set-Cookie` are redacted and JSON bodies are
+/// pretty-printed; see [`format_http_response`](crate::format_http_response).
+///
+/// ```no_run
+/// # use insta::*;
+/// let response = http::Response::builder()
+///     .header("content-type", "application/json")
+///     .body(r#"{"id":42}"#)
+///     .unwrap();
+/// assert_http_snapshot!(response);
+/// ```
+#[cfg(feature = "http")]
+#[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+#[macro_export]
+macro_rules! assert_http_snapshot {
+    ($($arg:tt)*) => {
+        $crate::_assert_snapshot_base!(transform=$crate::_macro_support::format_http_response, $($arg)*)
+    };
+}
+
+/// Checks a proptest property and snapshots the minimized counterexample.
+///
+/// The first argument is a [`proptest`] strategy, the second a closure that
+/// tests one value of it (with `prop_assert!` and friends, or by panicking).
+/// The outcome is snapshotted on every run: `no counterexample` if the
+/// property holds, otherwise the shrunk failing input and the failure.  A
+/// failing property thus fails the test until its counterexample is accepted
+/// as expected.  See [`run_proptest`](crate::run_proptest) for the format.
+///
+/// ```no_run
+/// # use insta::*;
+/// use proptest::prelude::*;
+///
+/// assert_proptest_snapshot!((0..100i32, 0..100i32), |(a, b)| {
+///     prop_assert!(a + b >= a);
+///     Ok(())
+/// });
+/// ```
+///
+/// Like [`assert_snapshot!`] an explicit name can be passed first.  The
+/// proptest configuration defaults to `Config::default()`, and can be passed
+/// as `config = ...` before the strategy.  Failures are persisted relative to
+/// the test file unless `source_file` is set in the configuration:
+///
+/// ```no_run
+/// # use insta::*;
+/// use proptest::prelude::*;
+/// use proptest::test_runner::Config;
+///
+/// assert_proptest_snapshot!("sum", config = Config::with_cases(1000), (0..100i32, 0..100i32), |(a, b)| {
+///     prop_assert!(a + b >= a);
+///     Ok(())
+/// });
+/// ```
+#[cfg(feature = "proptest")]
+#[cfg_attr(docsrs, doc(cfg(feature = "proptest")))]
+#[macro_export]
+macro_rules! assert_proptest_snapshot {
+    (config = $config:expr, $strategy:expr, $test:expr $(,)?) => {
+        $crate::assert_proptest_snapshot!(
+            $crate::_macro_support::AutoName,
+            config = $config,
+            $strategy,
+            $test
+        )
+    };
+    ($name:expr, config = $config:expr, $strategy:expr, $test:expr $(,)?) => {{
+        let mut config: $crate::_macro_support::ProptestConfig = $config;
+        if config.source_file.is_none() {
+            config.source_file = Some(file!());
+        }
+        let counterexample = $crate::_macro_support::run_proptest(config, &$strategy, $test);
+        $crate::assert_snapshot!($name, counterexample, stringify!($test));
+    }};
+    ($strategy:expr, $test:expr $(,)?) => {
+        $crate::assert_proptest_snapshot!($crate::_macro_support::AutoName, $strategy, $test)
+    };
+    ($name:expr, $strategy:expr, $test:expr $(,)?) => {
+        $crate::assert_proptest_snapshot!(
+            $name,
+            config = $crate::_macro_support::ProptestConfig::default(),
+            $strategy,
+            $test
+        )
+    };
+}
+
+/// Asserts a snapshot of the `tracing` events emitted by a block.
+///
+/// The block runs with a capturing subscriber (see [`capture_tracing`](crate::capture_tracing))
+/// and the rendered events are snapshotted like a string.  Timestamps and
+/// span ids are not part of the output.
+///
+/// ```no_run
+/// # use insta::*;
+/// // implicitly named
+/// assert_tracing_snapshot!({ tracing::info!("hello") });
+/// // named
+/// assert_tracing_snapshot!("greeting", { tracing::info!("hello") });
+/// // inline
+/// assert_tracing_snapshot!({ tracing::info!("hello") }, @"INFO rust_out: hello");
+/// ```
+#[cfg(feature = "tracing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tracing")))]
+#[macro_export]
+macro_rules! assert_tracing_snapshot {
+    ($($arg:tt)*) => {
+        $crate::_assert_captured_snapshot!($crate::_macro_support::capture_tracing, $($arg)*)
+    };
+}
+
+/// Asserts a snapshot of the `log` records emitted by a block.
+///
+/// The block runs with a capturing logger (see [`capture_log`](crate::capture_log))
+/// and the rendered records are snapshotted like a string.
+///
+/// ```no_run
+/// # use insta::*;
+/// // implicitly named
+/// assert_log_snapshot!({ log::info!("hello") });
+/// // named
+/// assert_log_snapshot!("greeting", { log::info!("hello") });
+/// // inline
+/// assert_log_snapshot!({ log::info!("hello") }, @"INFO rust_out: hello");
+/// ```
+#[cfg(feature = "log")]
+#[cfg_attr(docsrs, doc(cfg(feature = "log")))]
+#[macro_export]
+macro_rules! assert_log_snapshot {
+    ($($arg:tt)*) => {
+        $crate::_assert_captured_snapshot!($crate::_macro_support::capture_log, $($arg)*)
+    };
+}
+
+// Runs a block with a capture function and snapshots the captured output.
+// The block is used as debug expression.
+#[cfg(any(feature = "tracing", feature = "log"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _assert_captured_snapshot {
+    ($capture:path, $body:block, @$snapshot:literal $(,)?) => {
+        $crate::assert_snapshot!(
+            #[allow(clippy::needless_raw_string_hashes)]
+            $crate::_macro_support::InlineValue($snapshot),
+            $capture(|| $body),
+            stringify!($body)
+        )
+    };
+    ($capture:path, $body:block $(,)?) => {
+        $crate::assert_snapshot!(
+            $crate::_macro_support::AutoName,
+            $capture(|| $body),
+            stringify!($body)
+        )
+    };
+    ($capture:path, $name:expr, $body:block $(,)?) => {
+        $crate::assert_snapshot!($name, $capture(|| $body), stringify!($body))
+    };
+}
+
 /// Settings configuration macro.
 ///
 /// This macro lets you bind some [`Settings`](crate::Settings) temporarily.  The first argument
@@ -570,6 +856,59 @@ macro_rules! glob {
     }};
 }
 
+/// Runs a closure for all input files matching a glob, each as a separate test.
+///
+/// This works like [`glob!`] but is meant to be the `main` function of a test
+/// target with a custom harness.  Every matching file is registered as its own
+/// test case named after the file, so the files can be filtered with `cargo
+/// test <name>` and are reported individually.  The snapshots are named the
+/// same as they would be with [`glob!`].
+///
+/// The test target needs to opt out of the default harness in `Cargo.toml`:
+///
+/// ```toml
+/// [[test]]
+/// name = "inputs"
+/// harness = false
+/// ```
+///
+/// And then in `tests/inputs.rs`:
+///
+/// ```no_run
+/// use std::fs;
+///
+/// fn main() {
+///     insta::harness!("inputs/*.txt", |path| {
+///         let input = fs::read_to_string(path).unwrap();
+///         insta::assert_snapshot!(input.to_uppercase());
+///     });
+/// }
+/// ```
+///
+/// As with [`glob!`] a three-argument version allows specifying the base
+/// directory for the glob to start in.  The closure is run on the test
+/// threads, so it has to be `Send + Sync + 'static`.
+#[cfg(feature = "harness")]
+#[cfg_attr(docsrs, doc(cfg(feature = "harness")))]
+#[macro_export]
+macro_rules! harness {
+    ($base_path:expr, $glob:expr, $closure:expr) => {{
+        use std::path::Path;
+
+        let base = $crate::_get_workspace_root!()
+            .join(Path::new(file!()).parent().unwrap())
+            .join($base_path)
+            .to_path_buf();
+
+        let base = base.canonicalize().unwrap_or_else(|_| base);
+        $crate::_macro_support::harness_exec(&base, $glob, $closure)
+    }};
+
+    ($glob:expr, $closure:expr) => {{
+        $crate::harness!(".", $glob, $closure)
+    }};
+}
+
 /// Utility macro to permit a multi-snapshot run where all snapshots match.
 ///
 /// Within this block, insta will allow an assertion to be run more than once
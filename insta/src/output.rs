@@ -1,9 +1,10 @@
 use std::borrow::Cow;
 use std::{path::Path, time::Duration};
 
-use similar::{Algorithm, ChangeTag, TextDiff};
+use similar::{group_diff_ops, udiff::UnifiedDiffHunk, ChangeTag, DiffOp, TextDiff};
 
 use crate::content::yaml;
+use crate::diff::{DiffAlgorithm, DiffEngine};
 use crate::snapshot::{MetaData, Snapshot, SnapshotContents};
 use crate::utils::{format_rust_expression, style, term_width};
 
@@ -19,6 +20,8 @@ pub struct SnapshotPrinter<'a> {
     title: Option<&'a str>,
     line: Option<u32>,
     snapshot_file: Option<&'a Path>,
+    diff_engine: &'a dyn DiffEngine,
+    diff_context: Option<usize>,
 }
 
 impl<'a> SnapshotPrinter<'a> {
@@ -38,6 +41,8 @@ impl<'a> SnapshotPrinter<'a> {
             title: None,
             line: None,
             snapshot_file: None,
+            diff_engine: &DiffAlgorithm::Patience,
+            diff_context: Some(4),
         }
     }
 
@@ -58,6 +63,16 @@ impl<'a> SnapshotPrinter<'a> {
         self.title = title;
     }
 
+    pub fn set_diff_engine(&mut self, engine: &'a dyn DiffEngine) {
+        self.diff_engine = engine;
+    }
+
+    /// Sets the number of unchanged lines shown around changes, `None` shows
+    /// the snapshots in full.
+    pub fn set_diff_context(&mut self, context: Option<usize>) {
+        self.diff_context = context;
+    }
+
     pub fn set_line(&mut self, line: Option<u32>) {
         self.line = line;
     }
@@ -199,10 +214,8 @@ impl<'a> SnapshotPrinter<'a> {
             let new_text = new.as_deref().unwrap_or("");
 
             let newlines_matter = newlines_matter(old_text, new_text);
-            let diff = TextDiff::configure()
-                .algorithm(Algorithm::Patience)
-                .timeout(Duration::from_millis(500))
-                .diff_lines(old_text, new_text);
+            let (diff, groups) =
+                diff_lines(self.diff_engine, old_text, new_text, self.diff_context);
 
             if old.is_some() {
                 println!(
@@ -224,7 +237,7 @@ impl<'a> SnapshotPrinter<'a> {
             // equal (that would otherwise happen if the text snapshot is an empty string).
             let mut has_changes = old.is_none() || new.is_none();
 
-            for (idx, group) in diff.grouped_ops(4).iter().enumerate() {
+            for (idx, group) in groups.iter().enumerate() {
                 if idx > 0 {
                     println!("┈┈┈┈┈┈┈┈┈┈┈┈┼{:┈^1$}", "", width.saturating_sub(13));
                 }
@@ -291,6 +304,16 @@ impl<'a> SnapshotPrinter<'a> {
                     style("-").dim(),
                     style(" snapshots are matching").cyan(),
                 );
+            } else if self.diff_context.is_some() && !groups.is_empty() {
+                let (added, removed) = count_changed_lines(&groups);
+                println!("────────────┼{:─^1$}", "", width.saturating_sub(13));
+                println!(
+                    "{:>5} {:>5} │ {} {}",
+                    "",
+                    "",
+                    style(format_args!("{} added,", pluralize_lines(added))).green(),
+                    style(format_args!("{} removed", pluralize_lines(removed))).red(),
+                );
             }
 
             println!("────────────┴{:─^1$}", "", width.saturating_sub(13));
@@ -341,6 +364,84 @@ pub fn print_snapshot_summary(
     }
 }
 
+/// Renders the difference between two snapshots as an uncolored unified diff.
+///
+/// This is used where the diff is not shown on a terminal, such as in test
+/// reports.
+pub fn format_plain_diff(
+    engine: &dyn DiffEngine,
+    context: Option<usize>,
+    old_snapshot: Option<&Snapshot>,
+    new_snapshot: &Snapshot,
+) -> String {
+    fn text(contents: &SnapshotContents) -> String {
+        match contents {
+            SnapshotContents::Text(text) => text.to_string(),
+            SnapshotContents::Binary(_) => "<binary>\n".to_string(),
+        }
+    }
+
+    let old_text = old_snapshot.map(|x| text(x.contents())).unwrap_or_default();
+    let new_text = text(new_snapshot.contents());
+    let (diff, groups) = diff_lines(engine, &old_text, &new_text, context);
+    let mut rv = String::new();
+    for (idx, group) in groups.into_iter().filter(|x| !x.is_empty()).enumerate() {
+        if idx == 0 {
+            rv.push_str("--- old snapshot\n+++ new results\n");
+        }
+        rv.push_str(&UnifiedDiffHunk::new(group, &diff, false).to_string());
+    }
+    rv
+}
+
+/// Diffs two texts line by line with `engine`.
+///
+/// Returns the lines of both texts together with the changes grouped into
+/// hunks with `context` unchanged lines around them.  Without a context
+/// everything is a single hunk.
+fn diff_lines<'a>(
+    engine: &dyn DiffEngine,
+    old: &'a str,
+    new: &'a str,
+    context: Option<usize>,
+) -> (TextDiff<'a, 'a, 'a, str>, Vec<Vec<DiffOp>>) {
+    // the text diff only splits the texts into lines (the zero timeout keeps
+    // its own diff cheap), the changes come from the engine.
+    let diff = TextDiff::configure()
+        .timeout(Duration::ZERO)
+        .diff_lines(old, new);
+    let ops = engine.diff_lines(diff.old_slices(), diff.new_slices());
+    let groups = match context {
+        Some(context) => group_diff_ops(ops, context),
+        None if ops.iter().all(|x| matches!(x, DiffOp::Equal { .. })) => vec![],
+        None => vec![ops],
+    };
+    (diff, groups)
+}
+
+/// Counts the added and removed lines in the hunks of a diff.
+fn count_changed_lines(groups: &[Vec<DiffOp>]) -> (usize, usize) {
+    groups
+        .iter()
+        .flatten()
+        .fold((0, 0), |(added, removed), op| match *op {
+            DiffOp::Equal { .. } => (added, removed),
+            DiffOp::Delete { old_len, .. } => (added, removed + old_len),
+            DiffOp::Insert { new_len, .. } => (added + new_len, removed),
+            DiffOp::Replace {
+                old_len, new_len, ..
+            } => (added + new_len, removed + old_len),
+        })
+}
+
+fn pluralize_lines(n: usize) -> String {
+    if n == 1 {
+        "1 line".to_string()
+    } else {
+        format!("{} lines", n)
+    }
+}
+
 fn print_line(width: usize) {
     println!("{:─^1$}", "", width);
 }
@@ -424,9 +525,9 @@ fn print_info(metadata: &MetaData) {
         print_line(width);
     }
     if let Some(info) = metadata.private_info() {
-        let out = yaml::to_string(info);
-        // TODO: does the yaml output always start with '---'?
-        println!("{}", out.trim().strip_prefix("---").unwrap().trim_start());
+        let mut out = String::new();
+        yaml::to_writer(info, &mut out).unwrap();
+        println!("{}", out.trim_end());
         print_line(width);
     }
 }
@@ -449,3 +550,80 @@ fn test_invisible() {
         "␍␊\r\n␛␍\r␇␈␡␊\n"
     );
 }
+
+#[test]
+fn test_format_plain_diff_engine() {
+    use crate::snapshot::{TextSnapshotContents, TextSnapshotKind};
+
+    struct ReplaceAll;
+
+    impl DiffEngine for ReplaceAll {
+        fn diff_lines(&self, old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+            vec![DiffOp::Replace {
+                old_index: 0,
+                old_len: old.len(),
+                new_index: 0,
+                new_len: new.len(),
+            }]
+        }
+    }
+
+    let snapshot = |text: &str| {
+        Snapshot::from_components(
+            "test".into(),
+            None,
+            MetaData::default(),
+            TextSnapshotContents::new(text.into(), TextSnapshotKind::File).into(),
+        )
+    };
+    let old = snapshot("a\nb\nc\n");
+    let new = snapshot("a\nB\nc\n");
+
+    assert_eq!(
+        format_plain_diff(&DiffAlgorithm::Patience, Some(4), Some(&old), &new),
+        "--- old snapshot\n+++ new results\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n"
+    );
+    assert_eq!(
+        format_plain_diff(&ReplaceAll, Some(4), Some(&old), &new),
+        "--- old snapshot\n+++ new results\n@@ -1,3 +1,3 @@\n-a\n-b\n-c\n+a\n+B\n+c\n"
+    );
+    assert_eq!(
+        format_plain_diff(&DiffAlgorithm::Myers, Some(4), Some(&old), &old),
+        ""
+    );
+}
+
+#[test]
+fn test_format_plain_diff_context() {
+    use crate::snapshot::{TextSnapshotContents, TextSnapshotKind};
+
+    let snapshot = |text: String| {
+        Snapshot::from_components(
+            "test".into(),
+            None,
+            MetaData::default(),
+            TextSnapshotContents::new(text, TextSnapshotKind::File).into(),
+        )
+    };
+    let old = snapshot((1..=20).map(|x| format!("{}\n", x)).collect());
+    let new = snapshot(
+        (1..=20)
+            .map(|x| format!("{}\n", x * (x % 10).min(1)))
+            .collect(),
+    );
+
+    assert_eq!(
+        format_plain_diff(&DiffAlgorithm::Patience, Some(1), Some(&old), &new),
+        "--- old snapshot\n+++ new results\n@@ -9,3 +9,3 @@\n 9\n-10\n+0\n 11\n@@ -19,2 +19,2 @@\n 19\n-20\n+0\n"
+    );
+    let full = format_plain_diff(&DiffAlgorithm::Patience, None, Some(&old), &new);
+    assert!(full.starts_with("--- old snapshot\n+++ new results\n@@ -1,20 +1,20 @@\n 1\n"));
+    assert_eq!(
+        format_plain_diff(&DiffAlgorithm::Patience, None, Some(&old), &old),
+        ""
+    );
+    assert_eq!(
+        count_changed_lines(&diff_lines(&DiffAlgorithm::Patience, "a\nb\n", "c\n", Some(4)).1),
+        (1, 2)
+    );
+}
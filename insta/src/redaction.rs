@@ -4,6 +4,7 @@ use std::borrow::Cow;
 use std::fmt;
 
 use crate::content::Content;
+use crate::settings::Settings;
 
 #[derive(Debug)]
 pub struct SelectorParseError(Box<pest::error::Error<Rule>>);
@@ -24,22 +25,22 @@ impl SelectorParseError {
 /// path that the selector matched.
 #[derive(Clone, Debug)]
 #[cfg_attr(docsrs, doc(cfg(feature = "redactions")))]
-pub struct ContentPath<'a>(&'a [PathItem]);
+pub struct ContentPath<'a>(&'a [PathSegment<'a>]);
 
 impl fmt::Display for ContentPath<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         for item in self.0.iter() {
             write!(f, ".")?;
             match *item {
-                PathItem::Content(ref ctx) => {
-                    if let Some(s) = ctx.as_str() {
+                PathSegment::Key(key) => {
+                    if let Some(s) = key.as_str() {
                         write!(f, "{}", s)?;
                     } else {
                         write!(f, "<content>")?;
                     }
                 }
-                PathItem::Field(name) => write!(f, "{}", name)?,
-                PathItem::Index(idx, _) => write!(f, "{}", idx)?,
+                PathSegment::Field(name) => write!(f, "{}", name)?,
+                PathSegment::Index(idx, _) => write!(f, "{}", idx)?,
             }
         }
         Ok(())
@@ -189,11 +190,13 @@ pub fn rounded_redaction(decimals: usize) -> Redaction {
 
 impl Redaction {
     /// Performs the redaction of the value at the given path.
-    fn redact(&self, value: Content, path: &[PathItem]) -> Content {
-        match *self {
+    pub(crate) fn redact(&self, value: &mut Content, path: &[PathSegment<'_>]) {
+        *value = match *self {
             Redaction::Static(ref new_val) => new_val.clone(),
-            Redaction::Dynamic(ref callback) => callback(value, ContentPath(path)),
-        }
+            Redaction::Dynamic(ref callback) => {
+                callback(std::mem::replace(value, Content::None), ContentPath(path))
+            }
+        };
     }
 }
 
@@ -201,27 +204,31 @@ impl Redaction {
 #[grammar = "select_grammar.pest"]
 pub struct SelectParser;
 
-#[derive(Debug)]
-pub enum PathItem {
-    Content(Content),
+/// A single step of the path to a value, borrowed from the value itself.
+#[derive(Debug, Clone, Copy)]
+pub enum PathSegment<'a> {
+    /// A key in a map.
+    Key(&'a Content),
+    /// A struct field (or the `$key` pseudo field).
     Field(&'static str),
+    /// Index into a sequence and the length of the sequence.
     Index(u64, u64),
 }
 
-impl PathItem {
+impl PathSegment<'_> {
     fn as_str(&self) -> Option<&str> {
         match *self {
-            PathItem::Content(ref content) => content.as_str(),
-            PathItem::Field(s) => Some(s),
-            PathItem::Index(..) => None,
+            PathSegment::Key(key) => key.as_str(),
+            PathSegment::Field(s) => Some(s),
+            PathSegment::Index(..) => None,
         }
     }
 
     fn as_u64(&self) -> Option<u64> {
         match *self {
-            PathItem::Content(ref content) => content.as_u64(),
-            PathItem::Field(_) => None,
-            PathItem::Index(idx, _) => Some(idx),
+            PathSegment::Key(key) => key.as_u64(),
+            PathSegment::Field(_) => None,
+            PathSegment::Index(idx, _) => Some(idx),
         }
     }
 
@@ -234,7 +241,7 @@ impl PathItem {
             }
         }
         let (idx, len) = match *self {
-            PathItem::Index(idx, len) => (idx as i64, len as i64),
+            PathSegment::Index(idx, len) => (idx as i64, len as i64),
             _ => return false,
         };
         match (start, end) {
@@ -257,9 +264,64 @@ pub enum Segment<'a> {
     Range(Option<i64>, Option<i64>),
 }
 
+/// A pending step of the redaction traversal.
+enum Visit<'c> {
+    /// A value, and the segment that leads to it from its parent.
+    Value(Option<PathSegment<'c>>, &'c mut Content),
+    /// A map entry.  The key is redacted before the value is visited.
+    Entry(&'c mut (Content, Content)),
+}
+
 #[derive(Debug, Clone)]
 pub struct Selector<'a> {
     selectors: Vec<Vec<Segment<'a>>>,
+    /// Alternatives prefixed with `!`.  Values they match are never
+    /// redacted, and neither is anything below them.
+    exclusions: Vec<Vec<Segment<'a>>>,
+}
+
+impl fmt::Display for Selector<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let alternatives = self
+            .selectors
+            .iter()
+            .map(|x| ("", x))
+            .chain(self.exclusions.iter().map(|x| ("!", x)));
+        for (idx, (prefix, selector)) in alternatives.enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", prefix)?;
+            if selector.is_empty() {
+                write!(f, ".")?;
+            }
+            for segment in selector {
+                match *segment {
+                    Segment::DeepWildcard => write!(f, ".**")?,
+                    Segment::Wildcard => write!(f, ".*")?,
+                    Segment::Key(ref key) => {
+                        if key
+                            .chars()
+                            .all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+                        {
+                            write!(f, ".{}", key)?
+                        } else {
+                            write!(f, "[{:?}]", key)?
+                        }
+                    }
+                    Segment::Index(idx) => write!(f, "[{}]", idx)?,
+                    Segment::Range(start, end) => {
+                        let bound = |x: Option<i64>| x.map(|x| x.to_string()).unwrap_or_default();
+                        match (start, end) {
+                            (None, None) => write!(f, "[]")?,
+                            _ => write!(f, "[{}:{}]", bound(start), bound(end))?,
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 impl<'a> Selector<'a> {
@@ -269,13 +331,19 @@ impl<'a> Selector<'a> {
             .map_err(SelectorParseError)?
             .next()
             .unwrap();
+        let span = pair.as_span();
         let mut rv = vec![];
+        let mut exclusions = vec![];
 
         for selector_pair in pair.into_inner() {
-            match selector_pair.as_rule() {
+            let (selector_pair, excluded) = match selector_pair.as_rule() {
                 Rule::EOI => break,
-                other => assert_eq!(other, Rule::selector),
-            }
+                Rule::exclusion => (selector_pair.into_inner().next().unwrap(), true),
+                other => {
+                    assert_eq!(other, Rule::selector);
+                    (selector_pair, false)
+                }
+            };
             let mut segments = vec![];
             let mut have_deep_wildcard = false;
             for segment_pair in selector_pair.into_inner() {
@@ -346,16 +414,33 @@ impl<'a> Selector<'a> {
                     _ => unreachable!(),
                 });
             }
-            rv.push(segments);
+            if excluded {
+                exclusions.push(segments);
+            } else {
+                rv.push(segments);
+            }
         }
 
-        Ok(Selector { selectors: rv })
+        if rv.is_empty() {
+            return Err(SelectorParseError(Box::new(
+                pest::error::Error::new_from_span(
+                    pest::error::ErrorVariant::CustomError {
+                        message: "selector only consists of exclusions".into(),
+                    },
+                    span,
+                ),
+            )));
+        }
+
+        Ok(Selector {
+            selectors: rv,
+            exclusions,
+        })
     }
 
     pub fn make_static(self) -> Selector<'static> {
-        Selector {
-            selectors: self
-                .selectors
+        fn make_static(selectors: Vec<Vec<Segment<'_>>>) -> Vec<Vec<Segment<'static>>> {
+            selectors
                 .into_iter()
                 .map(|parts| {
                     parts
@@ -369,11 +454,16 @@ impl<'a> Selector<'a> {
                         })
                         .collect()
                 })
-                .collect(),
+                .collect()
+        }
+
+        Selector {
+            selectors: make_static(self.selectors),
+            exclusions: make_static(self.exclusions),
         }
     }
 
-    fn segment_is_match(&self, segment: &Segment, element: &PathItem) -> bool {
+    fn segment_is_match(&self, segment: &Segment, element: &PathSegment) -> bool {
         match *segment {
             Segment::Wildcard => true,
             Segment::DeepWildcard => true,
@@ -383,7 +473,7 @@ impl<'a> Selector<'a> {
         }
     }
 
-    fn selector_is_match(&self, selector: &[Segment], path: &[PathItem]) -> bool {
+    fn selector_is_match(&self, selector: &[Segment], path: &[PathSegment]) -> bool {
         if let Some(idx) = selector.iter().position(|x| *x == Segment::DeepWildcard) {
             let forward_sel = &selector[..idx];
             let backward_sel = &selector[idx + 1..];
@@ -418,130 +508,232 @@ impl<'a> Selector<'a> {
         }
     }
 
-    pub fn is_match(&self, path: &[PathItem]) -> bool {
+    /// Checks if a path below `path` can still match.  This is the case if
+    /// the path matches the beginning of a selector, up to a deep wildcard.
+    fn can_match_below(&self, path: &[PathSegment]) -> bool {
+        self.selectors.iter().any(|selector| {
+            let prefix = match selector.iter().position(|x| *x == Segment::DeepWildcard) {
+                Some(idx) => &selector[..idx.min(path.len())],
+                None if path.len() < selector.len() => &selector[..path.len()],
+                None => return false,
+            };
+            prefix
+                .iter()
+                .zip(path.iter())
+                .all(|(segment, element)| self.segment_is_match(segment, element))
+        })
+    }
+
+    /// Checks if `path` or a path above it is excluded.
+    fn is_excluded(&self, path: &[PathSegment]) -> bool {
+        self.exclusions.iter().any(|selector| {
+            (0..=path.len()).any(|len| self.selector_is_match(selector, &path[..len]))
+        })
+    }
+
+    pub fn is_match(&self, path: &[PathSegment]) -> bool {
         for selector in &self.selectors {
             if self.selector_is_match(selector, path) {
-                return true;
+                return !self.is_excluded(path);
             }
         }
         false
     }
 
-    pub fn redact(&self, value: Content, redaction: &Redaction) -> Content {
-        self.redact_impl(value, redaction, &mut vec![])
+    pub fn redact(&self, mut value: Content, redaction: &Redaction) -> Content {
+        let matched = self.redact_impl(&mut value, redaction, &mut vec![]);
+        check_redaction_matched(self, matched);
+        value
     }
 
-    fn redact_seq(
+    /// Redacts the value, returning whether the selector matched anything.
+    fn redact_impl<'c>(
         &self,
-        seq: Vec<Content>,
+        value: &'c mut Content,
         redaction: &Redaction,
-        path: &mut Vec<PathItem>,
-    ) -> Vec<Content> {
-        let len = seq.len();
-        seq.into_iter()
-            .enumerate()
-            .map(|(idx, value)| {
-                path.push(PathItem::Index(idx as u64, len as u64));
-                let new_value = self.redact_impl(value, redaction, path);
-                path.pop();
-                new_value
-            })
-            .collect()
-    }
+        path: &mut Vec<PathSegment<'c>>,
+    ) -> bool {
+        let mut matched = false;
+        // The traversal uses an explicit stack so that deeply nested content
+        // cannot overflow the stack.  Every item carries the length of the
+        // path of its parent.  Children are pushed in reverse so that they
+        // are visited in order.
+        let mut stack = vec![(path.len(), Visit::Value(None, value))];
+
+        while let Some((depth, visit)) = stack.pop() {
+            path.truncate(depth);
+            let value = match visit {
+                Visit::Value(segment, value) => {
+                    path.extend(segment);
+                    value
+                }
+                Visit::Entry((key, value)) => {
+                    path.push(PathSegment::Field("$key"));
+                    matched |= self.redact_key(key, redaction, path);
+                    path.pop();
+                    stack.push((depth, Visit::Value(Some(PathSegment::Key(key)), value)));
+                    continue;
+                }
+            };
 
-    fn redact_struct(
-        &self,
-        seq: Vec<(&'static str, Content)>,
-        redaction: &Redaction,
-        path: &mut Vec<PathItem>,
-    ) -> Vec<(&'static str, Content)> {
-        seq.into_iter()
-            .map(|(key, value)| {
-                path.push(PathItem::Field(key));
-                let new_value = self.redact_impl(value, redaction, path);
-                path.pop();
-                (key, new_value)
-            })
-            .collect()
-    }
+            if self.is_excluded(path) {
+                continue;
+            }
+            if self.is_match(path) {
+                redaction.redact(value, path);
+                matched = true;
+                continue;
+            }
+            if !self.can_match_below(path) {
+                continue;
+            }
 
-    fn redact_impl(
-        &self,
-        value: Content,
-        redaction: &Redaction,
-        path: &mut Vec<PathItem>,
-    ) -> Content {
-        if self.is_match(path) {
-            redaction.redact(value, path)
-        } else {
+            let depth = path.len();
             match value {
-                Content::Map(map) => Content::Map(
-                    map.into_iter()
-                        .map(|(key, value)| {
-                            path.push(PathItem::Field("$key"));
-                            let new_key = self.redact_impl(key.clone(), redaction, path);
-                            path.pop();
-
-                            path.push(PathItem::Content(key));
-                            let new_value = self.redact_impl(value, redaction, path);
-                            path.pop();
-
-                            (new_key, new_value)
-                        })
-                        .collect(),
-                ),
-                Content::Seq(seq) => Content::Seq(self.redact_seq(seq, redaction, path)),
-                Content::Tuple(seq) => Content::Tuple(self.redact_seq(seq, redaction, path)),
-                Content::TupleStruct(name, seq) => {
-                    Content::TupleStruct(name, self.redact_seq(seq, redaction, path))
-                }
-                Content::TupleVariant(name, variant_index, variant, seq) => Content::TupleVariant(
-                    name,
-                    variant_index,
-                    variant,
-                    self.redact_seq(seq, redaction, path),
-                ),
-                Content::Struct(name, seq) => {
-                    Content::Struct(name, self.redact_struct(seq, redaction, path))
+                Content::Map(map) => {
+                    stack.extend(
+                        map.iter_mut()
+                            .rev()
+                            .map(|entry| (depth, Visit::Entry(entry))),
+                    );
                 }
-                Content::StructVariant(name, variant_index, variant, seq) => {
-                    Content::StructVariant(
-                        name,
-                        variant_index,
-                        variant,
-                        self.redact_struct(seq, redaction, path),
-                    )
+                Content::Seq(seq)
+                | Content::Tuple(seq)
+                | Content::TupleStruct(_, seq)
+                | Content::TupleVariant(_, _, _, seq) => {
+                    let len = seq.len() as u64;
+                    stack.extend(seq.iter_mut().enumerate().rev().map(|(idx, value)| {
+                        let segment = PathSegment::Index(idx as u64, len);
+                        (depth, Visit::Value(Some(segment), value))
+                    }));
                 }
-                Content::NewtypeStruct(name, inner) => Content::NewtypeStruct(
-                    name,
-                    Box::new(self.redact_impl(*inner, redaction, path)),
-                ),
-                Content::NewtypeVariant(name, index, variant_name, inner) => {
-                    Content::NewtypeVariant(
-                        name,
-                        index,
-                        variant_name,
-                        Box::new(self.redact_impl(*inner, redaction, path)),
-                    )
-                }
-                Content::Some(contents) => {
-                    Content::Some(Box::new(self.redact_impl(*contents, redaction, path)))
+                Content::Struct(_, fields) | Content::StructVariant(_, _, _, fields) => {
+                    stack.extend(fields.iter_mut().rev().map(|(key, value)| {
+                        (depth, Visit::Value(Some(PathSegment::Field(key)), value))
+                    }));
                 }
-                other => other,
+                Content::NewtypeStruct(_, inner)
+                | Content::NewtypeVariant(_, _, _, inner)
+                | Content::Some(inner) => stack.push((depth, Visit::Value(None, inner))),
+                _ => {}
             }
         }
+        matched
+    }
+
+    /// Redacts a map key at `path` (which ends in `$key`).
+    ///
+    /// The key is borrowed by the path of its value afterwards, so it has to
+    /// be handled separately.  The path only needs to be copied for keys
+    /// that are containers themselves.
+    fn redact_key(
+        &self,
+        key: &mut Content,
+        redaction: &Redaction,
+        path: &[PathSegment<'_>],
+    ) -> bool {
+        if self.is_match(path) {
+            redaction.redact(key, path);
+            true
+        } else if !matches!(
+            key,
+            Content::Bool(_)
+                | Content::U8(_)
+                | Content::U16(_)
+                | Content::U32(_)
+                | Content::U64(_)
+                | Content::U128(_)
+                | Content::I8(_)
+                | Content::I16(_)
+                | Content::I32(_)
+                | Content::I64(_)
+                | Content::I128(_)
+                | Content::F32(_)
+                | Content::F64(_)
+                | Content::Char(_)
+                | Content::String(_)
+                | Content::Bytes(_)
+                | Content::None
+                | Content::Unit
+                | Content::UnitStruct(_)
+                | Content::UnitVariant(..)
+        ) {
+            self.redact_impl(key, redaction, &mut path.to_vec())
+        } else {
+            false
+        }
+    }
+}
+
+/// Fails the assertion if a selector did not match anything and strict
+/// redactions are enabled.
+pub(crate) fn check_redaction_matched(selector: &Selector, matched: bool) {
+    if !matched && Settings::with(|settings| settings.strict_redactions()) {
+        panic!("redaction selector '{}' did not match anything", selector);
     }
 }
 
 #[test]
 fn test_range_checks() {
     use similar_asserts::assert_eq;
-    assert_eq!(PathItem::Index(0, 10).range_check(None, Some(-1)), true);
-    assert_eq!(PathItem::Index(9, 10).range_check(None, Some(-1)), false);
-    assert_eq!(PathItem::Index(0, 10).range_check(Some(1), Some(-1)), false);
-    assert_eq!(PathItem::Index(1, 10).range_check(Some(1), Some(-1)), true);
-    assert_eq!(PathItem::Index(9, 10).range_check(Some(1), Some(-1)), false);
-    assert_eq!(PathItem::Index(0, 10).range_check(Some(1), None), false);
-    assert_eq!(PathItem::Index(1, 10).range_check(Some(1), None), true);
-    assert_eq!(PathItem::Index(9, 10).range_check(Some(1), None), true);
+    assert_eq!(PathSegment::Index(0, 10).range_check(None, Some(-1)), true);
+    assert_eq!(PathSegment::Index(9, 10).range_check(None, Some(-1)), false);
+    assert_eq!(
+        PathSegment::Index(0, 10).range_check(Some(1), Some(-1)),
+        false
+    );
+    assert_eq!(
+        PathSegment::Index(1, 10).range_check(Some(1), Some(-1)),
+        true
+    );
+    assert_eq!(
+        PathSegment::Index(9, 10).range_check(Some(1), Some(-1)),
+        false
+    );
+    assert_eq!(PathSegment::Index(0, 10).range_check(Some(1), None), false);
+    assert_eq!(PathSegment::Index(1, 10).range_check(Some(1), None), true);
+    assert_eq!(PathSegment::Index(9, 10).range_check(Some(1), None), true);
+}
+
+#[test]
+fn test_selector_display() {
+    let selector = Selector::parse(r#".foo["bar baz"][0].*.**.$key[1:-1][:2], ., .x[]"#).unwrap();
+    assert_eq!(
+        selector.to_string(),
+        r#".foo["bar baz"][0].*.**.$key[1:-1][:2], ., .x[]"#
+    );
+}
+
+#[test]
+fn test_selector_exclusions() {
+    let foo = Content::from("foo");
+    let bar = Content::from("bar");
+    let selector = Selector::parse(".** | .x, !.foo").unwrap();
+    assert_eq!(selector.to_string(), ".**, .x, !.foo");
+    assert!(selector.is_match(&[PathSegment::Key(&bar)]));
+    assert!(!selector.is_match(&[PathSegment::Key(&foo)]));
+    assert!(!selector.is_match(&[PathSegment::Key(&foo), PathSegment::Key(&bar)]));
+    assert!(Selector::parse("!.foo").is_err());
+}
+
+#[test]
+fn test_can_match_below() {
+    let foo = Content::from("foo");
+    let bar = Content::from("bar");
+    let selector = Selector::parse(".foo[1:].bar, .a.**.b").unwrap();
+    assert!(selector.can_match_below(&[]));
+    assert!(selector.can_match_below(&[PathSegment::Key(&foo)]));
+    assert!(selector.can_match_below(&[PathSegment::Key(&foo), PathSegment::Index(1, 3)]));
+    assert!(!selector.can_match_below(&[PathSegment::Key(&foo), PathSegment::Index(0, 3)]));
+    assert!(!selector.can_match_below(&[PathSegment::Key(&bar)]));
+    assert!(!selector.can_match_below(&[
+        PathSegment::Key(&foo),
+        PathSegment::Index(1, 3),
+        PathSegment::Key(&bar),
+    ]));
+    assert!(selector.can_match_below(&[
+        PathSegment::Field("a"),
+        PathSegment::Key(&foo),
+        PathSegment::Key(&bar),
+    ]));
 }
@@ -1,24 +1,29 @@
 use std::cell::RefCell;
 use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
-use std::fs;
 use std::io::{ErrorKind, Write};
 use std::path::{Path, PathBuf};
-use std::rc::Rc;
 use std::str;
 use std::sync::{Arc, Mutex};
 use std::{borrow::Cow, env};
 
+use crate::content::Content;
+use crate::diff::DiffEngine;
+use crate::output::format_plain_diff;
 use crate::settings::Settings;
 use crate::snapshot::{
     MetaData, PendingInlineSnapshot, Snapshot, SnapshotContents, SnapshotKind, TextSnapshotContents,
 };
-use crate::utils::{path_to_storage, style};
-use crate::{env::get_tool_config, output::SnapshotPrinter};
+use crate::store::{FileSystemStore, ReadOnlyStore, SnapshotStore};
+use crate::utils::{has_file_system, path_to_storage, style};
+use crate::{
+    env::{get_cargo_workspace, get_tool_config},
+    output::SnapshotPrinter,
+};
 use crate::{
     env::{
-        memoize_snapshot_file, snapshot_update_behavior, OutputBehavior, SnapshotUpdateBehavior,
-        ToolConfig,
+        memoize_snapshot_file, record_assertion_result, snapshot_update_behavior, OutputBehavior,
+        SnapshotEvent, SnapshotUpdate, SnapshotUpdateBehavior, ToolConfig,
     },
     snapshot::TextSnapshotKind,
 };
@@ -201,7 +206,7 @@ fn detect_snapshot_name(function_name: &str, module_path: &str) -> Result<String
     };
 
     // next check if we need to add a suffix
-    let name = add_suffix_to_snapshot_name(Cow::Borrowed(name));
+    let name = add_suffix_to_snapshot_name(Cow::Borrowed(name), function_name);
     let key = format!("{}::{}", module_path.replace("::", "__"), name);
 
     // because fn foo and fn test_foo end up with the same snapshot name we
@@ -244,16 +249,115 @@ fn detect_snapshot_name(function_name: &str, module_path: &str) -> Result<String
     Ok(rv)
 }
 
+/// Adds the snapshot suffix from the settings to an explicit snapshot name.
+fn add_settings_suffix_to_snapshot_name<'a>(name: Cow<'a, str>) -> Cow<'a, str> {
+    Settings::with(|settings| match settings.snapshot_suffix() {
+        Some(suffix) => Cow::Owned(format!("{}@{}", name, suffix)),
+        None => name,
+    })
+}
+
 /// If there is a suffix on the settings, append it to the snapshot name.
-fn add_suffix_to_snapshot_name(name: Cow<'_, str>) -> Cow<'_, str> {
+///
+/// Without one, the case of a parameterized test is used as suffix.
+fn add_suffix_to_snapshot_name<'a>(name: Cow<'a, str>, function_name: &str) -> Cow<'a, str> {
     Settings::with(|settings| {
         settings
             .snapshot_suffix()
+            .map(Cow::Borrowed)
+            .or_else(|| detect_test_case(function_name).map(Cow::Owned))
             .map(|suffix| Cow::Owned(format!("{}@{}", name, suffix)))
             .unwrap_or_else(|| name)
     })
 }
 
+/// Removes the suffix from the settings from a snapshot name, if it has one
+/// and the suffix allows falling back to the snapshot without it.
+fn remove_snapshot_suffix(name: &str) -> Option<String> {
+    Settings::with(|settings| {
+        if !settings.snapshot_suffix_fallback() {
+            return None;
+        }
+        let suffix = format!("@{}", settings.snapshot_suffix()?);
+        let idx = name.rfind(&suffix)?;
+        Some(format!("{}{}", &name[..idx], &name[idx + suffix.len()..]))
+    })
+}
+
+/// Returns the name of the test that is currently running, if known.
+fn current_test_name() -> Option<String> {
+    match std::thread::current().name() {
+        // the test harness runs every test on a thread named after it, unless
+        // a test runner such as nextest runs one test per process.  Then the
+        // name is passed on the command line along with `--exact`.
+        Some("main") | None => {
+            let args = env::args().skip(1).collect::<Vec<_>>();
+            if !args.iter().any(|x| x == "--exact") {
+                return None;
+            }
+            test_filter_of_args(&args).map(|x| x.to_string())
+        }
+        Some(name) => Some(name.to_string()),
+    }
+}
+
+/// Returns the test name filter from the arguments of the libtest harness,
+/// skipping the values of options such as `--test-threads 1`.
+fn test_filter_of_args(args: &[String]) -> Option<&str> {
+    const OPTIONS_WITH_VALUE: &[&str] = &[
+        "--test-threads",
+        "--skip",
+        "--logfile",
+        "--color",
+        "--format",
+        "--shuffle-seed",
+        "-Z",
+    ];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if OPTIONS_WITH_VALUE.contains(&arg.as_str()) {
+            args.next();
+        } else if !arg.starts_with('-') {
+            return Some(arg);
+        }
+    }
+    None
+}
+
+/// Detects the case of a parameterized test.
+///
+/// `rstest` and `test-case` turn a test function into a module of the same
+/// name with one test per case, which run the original function.  In that
+/// case the running test is named `<function>::<case>` while the assertion
+/// runs in `<function>`.
+fn detect_test_case(function_name: &str) -> Option<String> {
+    // the test name does not include the crate name
+    let (_, function_path) = function_name.split_once("::")?;
+    let test_name = current_test_name()?;
+    let case = test_name.strip_prefix(function_path)?.strip_prefix("::")?;
+    normalize_case_name(case).map(|x| x.to_string())
+}
+
+/// Turns the name of a generated test into the name of its case.
+///
+/// The tests are named `case_<n>_<description>` by `rstest` and
+/// `test_case_<n>_<description>` by `test-case`.  If there is a description
+/// the index is dropped, so the snapshot stays the same when cases are
+/// reordered.
+fn normalize_case_name(test: &str) -> Option<&str> {
+    let rest = test
+        .strip_prefix("case_")
+        .or_else(|| test.strip_prefix("test_case_"))?;
+    let description = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+    if description.len() == rest.len() {
+        return None;
+    }
+    match description.trim_start_matches('_') {
+        "" => Some(test),
+        description => Some(description),
+    }
+}
+
 fn get_snapshot_filename(
     module_path: &str,
     assertion_file: &str,
@@ -263,13 +367,23 @@ fn get_snapshot_filename(
 ) -> PathBuf {
     let root = Path::new(cargo_workspace);
     let base = Path::new(assertion_file);
+    let tool_config = get_tool_config(cargo_workspace);
     Settings::with(|settings| {
         root.join(base.parent().unwrap())
-            .join(settings.snapshot_path())
+            .join(
+                settings
+                    .explicit_snapshot_path()
+                    .or_else(|| tool_config.snapshot_path())
+                    .unwrap_or_else(|| Path::new("snapshots")),
+            )
             .join({
                 use std::fmt::Write;
                 let mut f = String::new();
-                if settings.prepend_module_to_snapshot() {
+                if settings
+                    .explicit_prepend_module_to_snapshot()
+                    .or_else(|| tool_config.prepend_module_to_snapshot())
+                    .unwrap_or(true)
+                {
                     if is_doctest {
                         write!(
                             &mut f,
@@ -298,9 +412,13 @@ fn get_snapshot_filename(
 /// The context around a snapshot, such as the reference value, location, etc.
 /// (but not including the generated value). Responsible for saving the
 /// snapshot.
-#[derive(Debug)]
 struct SnapshotAssertionContext<'a> {
     tool_config: Arc<ToolConfig>,
+    /// Where snapshots are read from and written to, if anywhere.
+    store: Option<Arc<dyn SnapshotStore>>,
+    /// Renders the differences of failed assertions.
+    diff_engine: Arc<dyn DiffEngine>,
+    snapshot_update: SnapshotUpdate,
     workspace: &'a Path,
     module_path: &'a str,
     snapshot_name: Option<Cow<'a, str>>,
@@ -324,6 +442,28 @@ impl<'a> SnapshotAssertionContext<'a> {
         assertion_line: u32,
     ) -> Result<SnapshotAssertionContext<'a>, Box<dyn Error>> {
         let tool_config = get_tool_config(workspace);
+        let store = Settings::with(|settings| settings.snapshot_store().cloned()).or_else(|| {
+            // without a file system there is nowhere to keep snapshots by default
+            if has_file_system() {
+                let mut store = FileSystemStore::new();
+                store.set_fsync(tool_config.fsync());
+                Some(Arc::new(store) as Arc<dyn SnapshotStore>)
+            } else {
+                None
+            }
+        });
+        let store = match store {
+            Some(store) if tool_config.read_only() => {
+                let mut store = ReadOnlyStore::new(store);
+                store.set_report(tool_config.output_behavior() != OutputBehavior::Nothing);
+                Some(Arc::new(store) as Arc<dyn SnapshotStore>)
+            }
+            store => store,
+        };
+        let diff_engine = Settings::with(|settings| settings.diff_engine().cloned())
+            .unwrap_or_else(|| Arc::new(tool_config.diff_algorithm()));
+        let snapshot_update =
+            tool_config.snapshot_update_with(Settings::with(|settings| settings.snapshot_update()));
         let snapshot_name;
         let mut duplication_key = None;
         let mut snapshot_file = None;
@@ -334,7 +474,7 @@ impl<'a> SnapshotAssertionContext<'a> {
         match new_snapshot_value {
             SnapshotValue::FileText { name, .. } | SnapshotValue::Binary { name, .. } => {
                 let name = match &name {
-                    Some(name) => add_suffix_to_snapshot_name(name.clone()),
+                    Some(name) => add_settings_suffix_to_snapshot_name(name.clone()),
                     None => {
                         if is_doctest {
                             panic!("Cannot determine reliable names for snapshot in doctests.  Please use explicit names instead.");
@@ -354,8 +494,25 @@ impl<'a> SnapshotAssertionContext<'a> {
                     workspace,
                     is_doctest,
                 );
-                if fs::metadata(&file).is_ok() {
-                    old_snapshot = Some(Snapshot::from_file(&file)?);
+                if let Some(ref store) = store {
+                    old_snapshot = store.load(&file)?;
+                    // a suffixed snapshot falls back to the shared one until
+                    // it is written for the first time.
+                    if old_snapshot.is_none() {
+                        if let Some(shared_name) = remove_snapshot_suffix(&name) {
+                            let shared_file = get_snapshot_filename(
+                                module_path,
+                                assertion_file,
+                                &shared_name,
+                                workspace,
+                                is_doctest,
+                            );
+                            old_snapshot = store.load(&shared_file)?;
+                            if old_snapshot.is_some() {
+                                memoize_snapshot_file(&shared_file);
+                            }
+                        }
+                    }
                 }
                 snapshot_name = Some(name);
                 snapshot_file = Some(file);
@@ -404,6 +561,9 @@ impl<'a> SnapshotAssertionContext<'a> {
 
         Ok(SnapshotAssertionContext {
             tool_config,
+            store,
+            diff_engine,
+            snapshot_update,
             workspace,
             module_path,
             snapshot_name,
@@ -436,9 +596,15 @@ impl<'a> SnapshotAssertionContext<'a> {
             self.module_path.replace("::", "__"),
             self.snapshot_name.as_ref().map(|x| x.to_string()),
             Settings::with(|settings| MetaData {
-                source: Some(path_to_storage(Path::new(self.assertion_file))),
+                source: Some(
+                    self.tool_config
+                        .remap_path_prefixes(&path_to_storage(Path::new(self.assertion_file)))
+                        .into_owned(),
+                ),
                 assertion_line: Some(self.assertion_line),
-                description: settings.description().map(Into::into),
+                description: settings
+                    .description()
+                    .map(|x| self.tool_config.remap_path_prefixes(x).into_owned()),
                 expression: if settings.omit_expression() {
                     None
                 } else {
@@ -457,18 +623,23 @@ impl<'a> SnapshotAssertionContext<'a> {
 
     /// Cleanup logic for passing snapshots.
     pub fn cleanup_passing(&self) -> Result<(), Box<dyn Error>> {
+        let store = match self.store {
+            Some(ref store) => &**store,
+            None => return Ok(()),
+        };
+
         // let's just make sure there are no more pending files lingering
         // around.
         if let Some(ref snapshot_file) = self.snapshot_file {
             let snapshot_file = snapshot_file.clone().with_extension("snap.new");
-            fs::remove_file(snapshot_file).ok();
+            store.remove(&snapshot_file).ok();
         }
 
         // and add a null pending snapshot to a pending snapshot file if needed
         if let Some(ref pending_snapshots) = self.pending_snapshots_path {
-            if fs::metadata(pending_snapshots).is_ok() {
+            if store.exists(pending_snapshots) {
                 PendingInlineSnapshot::new(None, None, self.assertion_line)
-                    .save(pending_snapshots)?;
+                    .save_to(pending_snapshots, store)?;
             }
         }
         Ok(())
@@ -478,32 +649,32 @@ impl<'a> SnapshotAssertionContext<'a> {
     /// only ever remove maximum one file because we do this every time before we create a new
     /// pending snapshot.
     pub fn cleanup_previous_pending_binary_snapshots(&self) -> Result<(), Box<dyn Error>> {
+        let store = match self.store {
+            Some(ref store) => store,
+            None => return Ok(()),
+        };
         if let Some(ref path) = self.snapshot_file {
             // The file name to compare against has to be valid utf-8 as it is generated by this crate
             // out of utf-8 strings.
             let file_name_prefix = format!("{}.new.", path.file_name().unwrap().to_str().unwrap());
 
-            let read_dir = path.parent().unwrap().read_dir();
-
-            match read_dir {
-                Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
-                _ => (),
-            }
-
             // We have to loop over where whole directory here because there is no filesystem API
             // for getting files by prefix.
-            for entry in read_dir? {
-                let entry = entry?;
-                let entry_file_name = entry.file_name();
+            let entries = match store.list(path.parent().unwrap()) {
+                Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+                rv => rv?,
+            };
 
+            for entry in entries {
                 // We'll just skip over files with non-utf-8 names. The assumption being that those
                 // would not have been generated by this crate.
-                if entry_file_name
-                    .to_str()
+                if entry
+                    .file_name()
+                    .and_then(|x| x.to_str())
                     .map(|f| f.starts_with(&file_name_prefix))
                     .unwrap_or(false)
                 {
-                    std::fs::remove_file(entry.path())?;
+                    store.remove(&entry)?;
                 }
             }
         }
@@ -516,14 +687,22 @@ impl<'a> SnapshotAssertionContext<'a> {
         &self,
         new_snapshot: Snapshot,
     ) -> Result<SnapshotUpdateBehavior, Box<dyn Error>> {
+        let store = match self.store {
+            Some(ref store) => &**store,
+            None => return Ok(SnapshotUpdateBehavior::NoUpdate),
+        };
+
         // TODO: this seems to be making `unseen` be true when there is an
         // existing snapshot file; which seems wrong??
+        //
+        // A suffixed snapshot that fell back to the shared one counts as
+        // existing, so that changes to it are reviewed too.
         let unseen = self
             .snapshot_file
             .as_ref()
-            .map_or(false, |x| fs::metadata(x).is_ok());
+            .map_or(false, |x| store.exists(x) || self.old_snapshot.is_some());
         let should_print = self.tool_config.output_behavior() != OutputBehavior::Nothing;
-        let snapshot_update = snapshot_update_behavior(&self.tool_config, unseen);
+        let snapshot_update = snapshot_update_behavior(self.snapshot_update, unseen);
 
         // If snapshot_update is `InPlace` and we have an inline snapshot, then
         // use `NewFile`, since we can't use `InPlace` for inline. `cargo-insta`
@@ -535,12 +714,43 @@ impl<'a> SnapshotAssertionContext<'a> {
             } else {
                 snapshot_update
             };
+        // Nothing is written in read-only mode, so the snapshot cannot be
+        // accepted right away and has to stay a failure.
+        let snapshot_update = if self.tool_config.read_only() {
+            match snapshot_update {
+                SnapshotUpdateBehavior::InPlace => SnapshotUpdateBehavior::NewFile,
+                other => other,
+            }
+        } else {
+            snapshot_update
+        };
+        // the read-only store reports what it would have written instead, and
+        // listeners are not told about files that were never written.
+        let report_writes = should_print && !self.tool_config.read_only();
+        let emit_events = !self.tool_config.read_only();
+
+        let source_file = self.workspace.join(self.assertion_file);
+        let event = SnapshotEvent {
+            event: "created",
+            snapshot_name: self.snapshot_name.as_deref(),
+            snapshot_file: self.snapshot_file.as_deref(),
+            pending_file: None,
+            source_file: Some(&source_file),
+            line: Some(self.assertion_line),
+        };
 
         match snapshot_update {
             SnapshotUpdateBehavior::InPlace => {
                 if let Some(ref snapshot_file) = self.snapshot_file {
-                    new_snapshot.save(snapshot_file)?;
-                    if should_print {
+                    new_snapshot.save_to(snapshot_file, store)?;
+                    if emit_events {
+                        SnapshotEvent {
+                            event: "updated",
+                            ..event
+                        }
+                        .emit();
+                    }
+                    if report_writes {
                         elog!(
                             "{} {}",
                             if unseen {
@@ -559,8 +769,15 @@ impl<'a> SnapshotAssertionContext<'a> {
             SnapshotUpdateBehavior::NewFile => {
                 if let Some(ref snapshot_file) = self.snapshot_file {
                     // File snapshot
-                    let new_path = new_snapshot.save_new(snapshot_file)?;
-                    if should_print {
+                    let new_path = new_snapshot.save_new(snapshot_file, store)?;
+                    if emit_events {
+                        SnapshotEvent {
+                            pending_file: Some(&new_path),
+                            ..event
+                        }
+                        .emit();
+                    }
+                    if report_writes {
                         elog!(
                             "{} {}",
                             style("stored new snapshot").green(),
@@ -582,7 +799,14 @@ impl<'a> SnapshotAssertionContext<'a> {
                         self.old_snapshot.clone(),
                         self.assertion_line,
                     )
-                    .save(self.pending_snapshots_path.as_ref().unwrap())?;
+                    .save_to(self.pending_snapshots_path.as_ref().unwrap(), store)?;
+                    if emit_events {
+                        SnapshotEvent {
+                            pending_file: self.pending_snapshots_path.as_deref(),
+                            ..event
+                        }
+                        .emit();
+                    }
                 }
             }
             SnapshotUpdateBehavior::NoUpdate => {}
@@ -598,6 +822,8 @@ impl<'a> SnapshotAssertionContext<'a> {
         printer.set_line(Some(self.assertion_line));
         printer.set_snapshot_file(self.snapshot_file.as_deref());
         printer.set_title(Some("Snapshot Summary"));
+        printer.set_diff_engine(&*self.diff_engine);
+        printer.set_diff_context(self.tool_config.diff_context());
         printer.set_show_info(true);
         match self.tool_config.output_behavior() {
             OutputBehavior::Summary => {
@@ -611,9 +837,57 @@ impl<'a> SnapshotAssertionContext<'a> {
         }
     }
 
+    /// Records the outcome of the assertion if a test report was requested.
+    fn record_result(&self, new_snapshot: &Snapshot, pass: bool) {
+        record_assertion_result(|| {
+            let outcome = match (pass, &self.old_snapshot) {
+                (true, _) => "pass",
+                (false, Some(_)) => "fail",
+                (false, None) => "new",
+            };
+            let mut fields = vec![
+                (Content::from("outcome"), Content::from(outcome)),
+                (
+                    Content::from("snapshot_name"),
+                    Content::from(self.snapshot_name.as_deref().unwrap_or("unnamed snapshot")),
+                ),
+                (
+                    Content::from("module_path"),
+                    Content::from(self.module_path),
+                ),
+                (
+                    Content::from("assertion_file"),
+                    Content::from(self.assertion_file),
+                ),
+                (
+                    Content::from("assertion_line"),
+                    Content::from(self.assertion_line),
+                ),
+            ];
+            if let Some(ref snapshot_file) = self.snapshot_file {
+                fields.push((
+                    Content::from("snapshot_file"),
+                    Content::from(snapshot_file.display().to_string()),
+                ));
+            }
+            if !pass {
+                fields.push((
+                    Content::from("diff"),
+                    Content::from(format_plain_diff(
+                        &*self.diff_engine,
+                        self.tool_config.diff_context(),
+                        self.old_snapshot.as_ref(),
+                        new_snapshot,
+                    )),
+                ));
+            }
+            Content::Map(fields)
+        });
+    }
+
     /// Finalizes the assertion when the snapshot comparison fails, potentially
     /// panicking to fail the test
-    fn finalize(&self, update_result: SnapshotUpdateBehavior) {
+    fn finalize(&self, update_result: SnapshotUpdateBehavior, diff: Option<&str>) {
         // if we are in glob mode, we want to adjust the finalization
         // so that we do not show the hints immediately.
         let fail_fast = {
@@ -635,6 +909,7 @@ impl<'a> SnapshotAssertionContext<'a> {
             && update_result == SnapshotUpdateBehavior::NewFile
             && self.tool_config.output_behavior() != OutputBehavior::Nothing
             && !self.is_doctest
+            && !self.tool_config.read_only()
         {
             println!(
                 "{hint}",
@@ -677,9 +952,10 @@ impl<'a> SnapshotAssertionContext<'a> {
             }
 
             panic!(
-                "snapshot assertion for '{}' failed in line {}",
+                "snapshot assertion for '{}' failed in line {}{}",
                 self.snapshot_name.as_deref().unwrap_or("unnamed snapshot"),
-                self.assertion_line
+                self.assertion_line,
+                diff.map(|x| format!("\n\n{}", x)).unwrap_or_default(),
             );
         }
     }
@@ -713,6 +989,8 @@ fn record_snapshot_duplicate(
             printer.set_snapshot_file(ctx.snapshot_file.as_deref());
             printer.set_title(Some("Differences in Block"));
             printer.set_snapshot_hints("previous assertion", "current assertion");
+            printer.set_diff_engine(&*ctx.diff_engine);
+            printer.set_diff_context(ctx.tool_config.diff_context());
             if ctx.tool_config.output_behavior() == OutputBehavior::Diff {
                 printer.set_show_diff(true);
             }
@@ -777,9 +1055,11 @@ pub fn assert_snapshot(
 
     let content = match snapshot_value {
         SnapshotValue::FileText { content, .. } | SnapshotValue::InlineText { content, .. } => {
+            let content = ctx.tool_config.remap_path_prefixes(content);
+
             // apply filters if they are available
             #[cfg(feature = "filters")]
-            let content = Settings::with(|settings| settings.filters().apply_to(content));
+            let content = Settings::with(|settings| settings.filters().apply_to(&content));
 
             let kind = match ctx.snapshot_file {
                 Some(_) => TextSnapshotKind::File,
@@ -800,7 +1080,7 @@ pub fn assert_snapshot(
                 "file extensions starting with 'new.' are not allowed",
             );
 
-            SnapshotContents::Binary(Rc::new(content))
+            SnapshotContents::Binary(Arc::new(content))
         }
     };
 
@@ -832,25 +1112,159 @@ pub fn assert_snapshot(
         })
         .unwrap_or(false);
 
+    ctx.record_result(&new_snapshot, pass);
+
     if pass {
         ctx.cleanup_passing()?;
 
-        if matches!(
-            ctx.tool_config.snapshot_update(),
-            crate::env::SnapshotUpdate::Force
-        ) {
+        if ctx.snapshot_update == SnapshotUpdate::Force {
             ctx.update_snapshot(new_snapshot)?;
         }
     // otherwise print information and update snapshots.
     } else {
         ctx.print_snapshot_info(&new_snapshot);
+        // without a store (or in read-only mode) there is no pending snapshot
+        // to review, so the diff goes into the panic message instead
+        let diff = if ctx.store.is_some() && !ctx.tool_config.read_only() {
+            None
+        } else {
+            Some(format_plain_diff(
+                &*ctx.diff_engine,
+                ctx.tool_config.diff_context(),
+                ctx.old_snapshot.as_ref(),
+                &new_snapshot,
+            ))
+        };
         let update_result = ctx.update_snapshot(new_snapshot)?;
-        ctx.finalize(update_result);
+        ctx.finalize(update_result, diff.as_deref());
     }
 
     Ok(())
 }
 
+/// Asserts a text snapshot without the assertion macros.
+///
+/// This is meant for custom test harnesses that generate tests at runtime
+/// (for instance with `libtest-mimic`), where the snapshot name and where
+/// the assertion comes from are only known when the test runs.  It behaves
+/// like [`assert_snapshot!`](crate::assert_snapshot!) with an explicit name:
+///
+/// ```no_run
+/// use insta::internals::SnapshotAssertion;
+///
+/// # let output = "";
+/// SnapshotAssertion::new("case_1", output)
+///     .module_path("my_harness")
+///     .location("tests/harness.rs", 12)
+///     .assert();
+/// ```
+///
+/// The module path defaults to the name of the calling file and the location
+/// to the place where the assertion was created.  The workspace is found from
+/// `CARGO_MANIFEST_DIR`, which `cargo test` sets when running tests.
+pub struct SnapshotAssertion<'a> {
+    name: Cow<'a, str>,
+    content: Cow<'a, str>,
+    module_path: Option<Cow<'a, str>>,
+    file: Cow<'a, str>,
+    line: u32,
+    expression: Option<Cow<'a, str>>,
+    workspace: Option<PathBuf>,
+    settings: Option<Settings>,
+}
+
+impl<'a> SnapshotAssertion<'a> {
+    /// Creates an assertion of `content` against the snapshot `name`.
+    #[track_caller]
+    pub fn new<N: Into<Cow<'a, str>>, C: Into<Cow<'a, str>>>(
+        name: N,
+        content: C,
+    ) -> SnapshotAssertion<'a> {
+        let caller = std::panic::Location::caller();
+        SnapshotAssertion {
+            name: name.into(),
+            content: content.into(),
+            module_path: None,
+            file: Cow::Borrowed(caller.file()),
+            line: caller.line(),
+            expression: None,
+            workspace: None,
+            settings: None,
+        }
+    }
+
+    /// Sets the module path, which is part of the snapshot file name.
+    pub fn module_path<S: Into<Cow<'a, str>>>(mut self, module_path: S) -> Self {
+        self.module_path = Some(module_path.into());
+        self
+    }
+
+    /// Sets the source file (relative to the workspace) and line of the
+    /// assertion.  The snapshot is stored next to this file.
+    pub fn location<S: Into<Cow<'a, str>>>(mut self, file: S, line: u32) -> Self {
+        self.file = file.into();
+        self.line = line;
+        self
+    }
+
+    /// Sets the expression recorded in the snapshot metadata.
+    pub fn expression<S: Into<Cow<'a, str>>>(mut self, expression: S) -> Self {
+        self.expression = Some(expression.into());
+        self
+    }
+
+    /// Sets the workspace root the location is relative to.
+    pub fn workspace<P: Into<PathBuf>>(mut self, workspace: P) -> Self {
+        self.workspace = Some(workspace.into());
+        self
+    }
+
+    /// Runs the assertion with these settings bound.
+    pub fn with_settings(mut self, settings: Settings) -> Self {
+        self.settings = Some(settings);
+        self
+    }
+
+    /// Runs the assertion, panicking if the snapshot does not match.
+    pub fn assert(self) {
+        match self.settings {
+            Some(ref settings) => settings.bind(|| self.run()),
+            None => self.run(),
+        }
+        .unwrap()
+    }
+
+    fn run(&self) -> Result<(), Box<dyn Error>> {
+        let workspace = match self.workspace {
+            Some(ref workspace) => workspace.clone(),
+            None => {
+                let manifest_dir = env::var("CARGO_MANIFEST_DIR").map_err(|_| {
+                    "the workspace is unknown outside of cargo, set SnapshotAssertion::workspace"
+                })?;
+                get_cargo_workspace(&manifest_dir).to_path_buf()
+            }
+        };
+        let module_path = match self.module_path {
+            Some(ref module_path) => module_path.clone(),
+            None => Cow::Owned(
+                Path::new(&*self.file)
+                    .file_stem()
+                    .map(|x| x.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            ),
+        };
+        assert_snapshot(
+            (&*self.name, &*self.content).into(),
+            &workspace,
+            &format!("{}::{}", module_path, self.name),
+            &module_path,
+            &self.file,
+            self.line,
+            self.expression.as_deref().unwrap_or(&self.name),
+        )
+    }
+}
+
 #[allow(rustdoc::private_doc_tests)]
 /// Test snapshots in doctests.
 ///
@@ -877,3 +1291,36 @@ pub fn assert_snapshot(
 /// insta::assert_snapshot!(some_string, @"Coucou je suis un joli bug");
 /// ```
 const _DOCTEST1: bool = false;
+
+#[test]
+fn test_normalize_case_name() {
+    assert_eq!(normalize_case_name("case_1"), Some("case_1"));
+    assert_eq!(
+        normalize_case_name("case_12_empty_input"),
+        Some("empty_input")
+    );
+    assert_eq!(normalize_case_name("test_case_3_two"), Some("two"));
+    assert_eq!(
+        normalize_case_name("test_case_1_1_expects"),
+        Some("1_expects")
+    );
+    assert_eq!(normalize_case_name("case_x"), None);
+    assert_eq!(normalize_case_name("test_foo"), None);
+}
+
+#[test]
+fn test_test_filter_of_args() {
+    let args = |args: &[&str]| args.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        test_filter_of_args(&args(&["--test-threads", "1", "--exact", "a::case_1"])),
+        Some("a::case_1")
+    );
+    assert_eq!(
+        test_filter_of_args(&args(&["--exact", "a::case_1", "--nocapture"])),
+        Some("a::case_1")
+    );
+    assert_eq!(
+        test_filter_of_args(&args(&["--color=never", "--exact"])),
+        None
+    );
+}
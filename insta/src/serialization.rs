@@ -37,7 +37,11 @@ pub fn serialize_content(mut content: Content, format: SerializationFormat) -> S
     });
 
     match format {
-        SerializationFormat::Yaml => yaml::to_string(&content)[4..].to_string(),
+        SerializationFormat::Yaml => {
+            let mut buf = String::new();
+            yaml::to_writer(&content, &mut buf).unwrap();
+            buf
+        }
         SerializationFormat::Json => json::to_string_pretty(&content),
         SerializationFormat::JsonCompact => json::to_string_compact(&content),
         #[cfg(feature = "csv")]
@@ -88,14 +92,14 @@ pub fn serialize_content(mut content: Content, format: SerializationFormat) -> S
     }
 }
 
-pub fn serialize_value<S: Serialize>(s: &S, format: SerializationFormat) -> String {
+pub fn serialize_value<S: Serialize + ?Sized>(s: &S, format: SerializationFormat) -> String {
     let serializer = ContentSerializer::<ValueError>::new();
     let content = Serialize::serialize(s, serializer).unwrap();
     serialize_content(content, format)
 }
 
 #[cfg(feature = "redactions")]
-pub fn serialize_value_redacted<S: Serialize>(
+pub fn serialize_value_redacted<S: Serialize + ?Sized>(
     s: &S,
     redactions: &[(crate::redaction::Selector, crate::redaction::Redaction)],
     format: SerializationFormat,
@@ -160,3 +164,37 @@ fn test_yaml_serialization() {
       - run
     "###);
 }
+
+#[test]
+fn test_deeply_nested_content() {
+    fn nested(depth: usize) -> Content {
+        let mut content = Content::from("leaf");
+        for _ in 0..depth {
+            content = Content::Seq(vec![Content::Map(vec![(Content::from("x"), content)])]);
+        }
+        content
+    }
+
+    // nothing on the way renders or drops recursively, so even a small stack
+    // is fine
+    std::thread::Builder::new()
+        .stack_size(128 * 1024)
+        .spawn(|| {
+            let mut content = nested(2_000);
+            content.sort_maps();
+            #[cfg(feature = "redactions")]
+            {
+                // never matches, so the whole tree is walked
+                let selector = crate::redaction::Selector::parse(".**.missing").unwrap();
+                content = selector.redact(content, &"[redacted]".into());
+            }
+
+            let mut yaml = String::new();
+            yaml::to_writer(&content, &mut yaml).unwrap();
+            assert!(yaml.trim_end().ends_with("x: leaf"));
+            assert!(json::to_string_pretty(&content).contains("\"x\": \"leaf\""));
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}
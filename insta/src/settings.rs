@@ -12,27 +12,36 @@ use std::task::{Context, Poll};
 use crate::content::Content;
 #[cfg(feature = "serde")]
 use crate::content::ContentSerializer;
+use crate::diff::DiffEngine;
+use crate::env::SnapshotUpdate;
 #[cfg(feature = "filters")]
 use crate::filters::Filters;
 #[cfg(feature = "redactions")]
 use crate::redaction::{dynamic_redaction, sorted_redaction, ContentPath, Redaction, Selector};
+use crate::store::SnapshotStore;
 
 static DEFAULT_SETTINGS: Lazy<Arc<ActualSettings>> = Lazy::new(|| {
     Arc::new(ActualSettings {
         sort_maps: false,
-        snapshot_path: "snapshots".into(),
+        snapshot_path: None,
         snapshot_suffix: "".into(),
+        snapshot_suffix_fallback: false,
         input_file: None,
         description: None,
         info: None,
         omit_expression: false,
-        prepend_module_to_snapshot: true,
+        prepend_module_to_snapshot: None,
         #[cfg(feature = "redactions")]
         redactions: Redactions::default(),
+        #[cfg(feature = "redactions")]
+        strict_redactions: false,
         #[cfg(feature = "filters")]
         filters: Filters::default(),
         #[cfg(feature = "glob")]
         allow_empty_glob: false,
+        snapshot_store: None,
+        diff_engine: None,
+        snapshot_update: None,
     })
 });
 
@@ -60,19 +69,25 @@ impl<'a> From<Vec<(&'a str, Redaction)>> for Redactions {
 #[doc(hidden)]
 pub struct ActualSettings {
     pub sort_maps: bool,
-    pub snapshot_path: PathBuf,
+    pub snapshot_path: Option<PathBuf>,
     pub snapshot_suffix: String,
+    pub snapshot_suffix_fallback: bool,
     pub input_file: Option<PathBuf>,
     pub description: Option<String>,
     pub info: Option<Content>,
     pub omit_expression: bool,
-    pub prepend_module_to_snapshot: bool,
+    pub prepend_module_to_snapshot: Option<bool>,
     #[cfg(feature = "redactions")]
     pub redactions: Redactions,
+    #[cfg(feature = "redactions")]
+    pub strict_redactions: bool,
     #[cfg(feature = "filters")]
     pub filters: Filters,
     #[cfg(feature = "glob")]
     pub allow_empty_glob: bool,
+    pub snapshot_store: Option<Arc<dyn SnapshotStore>>,
+    pub diff_engine: Option<Arc<dyn DiffEngine>>,
+    pub snapshot_update: Option<SnapshotUpdate>,
 }
 
 impl ActualSettings {
@@ -81,11 +96,12 @@ impl ActualSettings {
     }
 
     pub fn snapshot_path<P: AsRef<Path>>(&mut self, path: P) {
-        self.snapshot_path = path.as_ref().to_path_buf();
+        self.snapshot_path = Some(path.as_ref().to_path_buf());
     }
 
     pub fn snapshot_suffix<I: Into<String>>(&mut self, suffix: I) {
         self.snapshot_suffix = suffix.into();
+        self.snapshot_suffix_fallback = true;
     }
 
     pub fn input_file<P: AsRef<Path>>(&mut self, p: P) {
@@ -97,7 +113,7 @@ impl ActualSettings {
     }
 
     #[cfg(feature = "serde")]
-    pub fn info<S: Serialize>(&mut self, s: &S) {
+    pub fn info<S: Serialize + ?Sized>(&mut self, s: &S) {
         let serializer = ContentSerializer::<ValueError>::new();
         let content = Serialize::serialize(s, serializer).unwrap();
         self.info = Some(content);
@@ -112,7 +128,7 @@ impl ActualSettings {
     }
 
     pub fn prepend_module_to_snapshot(&mut self, value: bool) {
-        self.prepend_module_to_snapshot = value;
+        self.prepend_module_to_snapshot = Some(value);
     }
 
     #[cfg(feature = "redactions")]
@@ -120,6 +136,11 @@ impl ActualSettings {
         self.redactions = r.into();
     }
 
+    #[cfg(feature = "redactions")]
+    pub fn strict_redactions(&mut self, value: bool) {
+        self.strict_redactions = value;
+    }
+
     #[cfg(feature = "filters")]
     pub fn filters<F: Into<Filters>>(&mut self, f: F) {
         self.filters = f.into();
@@ -129,6 +150,18 @@ impl ActualSettings {
     pub fn allow_empty_glob(&mut self, value: bool) {
         self.allow_empty_glob = value;
     }
+
+    pub fn snapshot_store(&mut self, store: Arc<dyn SnapshotStore>) {
+        self.snapshot_store = Some(store);
+    }
+
+    pub fn diff_engine(&mut self, engine: Arc<dyn DiffEngine>) {
+        self.diff_engine = Some(engine);
+    }
+
+    pub fn snapshot_update(&mut self, value: SnapshotUpdate) {
+        self.snapshot_update = Some(value);
+    }
 }
 
 /// Configures how insta operates at test time.
@@ -213,13 +246,21 @@ impl Settings {
     /// Setting this flag to `false` changes the snapshot filename to just
     /// `<name>.snap`.
     ///
-    /// The default value is `true`.
+    /// The default value is `true`, unless `behavior.prepend_module_to_snapshot`
+    /// is set in the insta config file.
     pub fn set_prepend_module_to_snapshot(&mut self, value: bool) {
         self._private_inner_mut().prepend_module_to_snapshot(value);
     }
 
     /// Returns the current value for module name prepending.
+    ///
+    /// This does not take the insta config file into account.
     pub fn prepend_module_to_snapshot(&self) -> bool {
+        self.inner.prepend_module_to_snapshot.unwrap_or(true)
+    }
+
+    /// Returns the module name prepending if it was set explicitly.
+    pub(crate) fn explicit_prepend_module_to_snapshot(&self) -> Option<bool> {
         self.inner.prepend_module_to_snapshot
     }
 
@@ -248,10 +289,29 @@ impl Settings {
     /// the snapshot would be named `"snapshot"` it turns into `"snapshot@foo"`.
     /// This is useful to separate snapshots if you want to use test
     /// parameterization.
+    ///
+    /// As long as no suffixed snapshot exists, the assertion compares against
+    /// the snapshot without the suffix.  This allows keeping a shared
+    /// snapshot and only adding suffixed ones where the output differs, for
+    /// instance with the suffix set to [`std::env::consts::OS`].  The
+    /// suffixes that `glob!` and `harness!` derive from the input files do
+    /// not fall back like this.
     pub fn set_snapshot_suffix<I: Into<String>>(&mut self, suffix: I) {
         self._private_inner_mut().snapshot_suffix(suffix);
     }
 
+    /// Sets the snapshot suffix for the current input file of `glob!` or
+    /// `harness!`.
+    ///
+    /// Unlike with [`Self::set_snapshot_suffix`] there is no fallback to the
+    /// snapshot without the suffix, which belongs to none of the inputs.
+    #[cfg(any(feature = "glob", feature = "harness"))]
+    pub(crate) fn set_input_file_suffix(&mut self, suffix: &str) {
+        let inner = self._private_inner_mut();
+        inner.snapshot_suffix(suffix);
+        inner.snapshot_suffix_fallback = false;
+    }
+
     /// Removes the snapshot suffix.
     pub fn remove_snapshot_suffix(&mut self) {
         self.set_snapshot_suffix("");
@@ -266,6 +326,11 @@ impl Settings {
         }
     }
 
+    /// Returns whether to fall back to the snapshot without the suffix.
+    pub(crate) fn snapshot_suffix_fallback(&self) -> bool {
+        self.inner.snapshot_suffix_fallback
+    }
+
     /// Sets the input file reference.
     ///
     /// This value is completely unused by the snapshot testing system but it
@@ -322,7 +387,7 @@ impl Settings {
     /// Alternatively you can use [`Self::set_raw_info`] instead.
     #[cfg(feature = "serde")]
     #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
-    pub fn set_info<S: Serialize>(&mut self, s: &S) {
+    pub fn set_info<S: Serialize + ?Sized>(&mut self, s: &S) {
         self._private_inner_mut().info(s);
     }
 
@@ -363,8 +428,14 @@ impl Settings {
     /// This can be useful if redactions must be shared across multiple
     /// snapshots.
     ///
-    /// Note that this only applies to snapshots that undergo serialization
-    /// (eg: does not work for [`assert_debug_snapshot!`](crate::assert_debug_snapshot!).)
+    /// A selector can list alternatives separated by `,` or `|`.  Values
+    /// matched by an alternative prefixed with `!` are left alone, along with
+    /// everything below them, so `.user.*, !.user.id` redacts every field of
+    /// `user` but `id`.
+    ///
+    /// Note that this only applies to snapshots that undergo serialization,
+    /// and to [`assert_debug_snapshot!`](crate::assert_debug_snapshot!) when
+    /// redactions are passed to it.
     #[cfg(feature = "redactions")]
     #[cfg_attr(docsrs, doc(cfg(feature = "redactions")))]
     pub fn add_redaction<R: Into<Redaction>>(&mut self, selector: &str, replacement: R) {
@@ -421,10 +492,31 @@ impl Settings {
         self._private_inner_mut().redactions.0.clear();
     }
 
+    /// Fails assertions if one of their redactions does not match anything.
+    ///
+    /// A typo in a selector otherwise goes unnoticed, as the redaction is
+    /// then just not applied.  With this enabled the assertion panics with
+    /// the selector instead.  This applies to the redactions passed to the
+    /// assertion macros and to the ones of the settings.
+    ///
+    /// The default value is `false`.
+    #[cfg(feature = "redactions")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "redactions")))]
+    pub fn set_strict_redactions(&mut self, value: bool) {
+        self._private_inner_mut().strict_redactions(value);
+    }
+
+    /// Returns true if redactions that don't match fail assertions.
+    #[cfg(feature = "redactions")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "redactions")))]
+    pub fn strict_redactions(&self) -> bool {
+        self.inner.strict_redactions
+    }
+
     /// Iterate over the redactions.
     #[cfg(feature = "redactions")]
     #[cfg_attr(docsrs, doc(cfg(feature = "redactions")))]
-    pub(crate) fn iter_redactions(&self) -> impl Iterator<Item = (&Selector, &Redaction)> {
+    pub(crate) fn iter_redactions(&self) -> impl Iterator<Item = (&Selector<'_>, &Redaction)> {
         self.inner.redactions.0.iter().map(|(a, b)| (a, &**b))
     }
 
@@ -480,14 +572,90 @@ impl Settings {
     ///
     /// If not absolute it's relative to where the test is in.
     ///
-    /// Defaults to `snapshots`.
+    /// Defaults to `snapshots`, unless `behavior.snapshot_path` is set in the
+    /// insta config file.
     pub fn set_snapshot_path<P: AsRef<Path>>(&mut self, path: P) {
         self._private_inner_mut().snapshot_path(path);
     }
 
     /// Returns the snapshot path.
+    ///
+    /// This does not take the insta config file into account.
     pub fn snapshot_path(&self) -> &Path {
-        &self.inner.snapshot_path
+        self.explicit_snapshot_path()
+            .unwrap_or_else(|| Path::new("snapshots"))
+    }
+
+    /// Returns the snapshot path if it was set explicitly.
+    pub(crate) fn explicit_snapshot_path(&self) -> Option<&Path> {
+        self.inner.snapshot_path.as_deref()
+    }
+
+    /// Sets the store snapshots are read from and written to.
+    ///
+    /// By default snapshots are files on disk.  Setting a different store,
+    /// such as a [`MemoryStore`](crate::MemoryStore), routes all snapshot
+    /// reads and writes of assertions made with these settings through it.
+    pub fn set_snapshot_store(&mut self, store: Arc<dyn SnapshotStore>) {
+        self._private_inner_mut().snapshot_store(store);
+    }
+
+    /// Removes the snapshot store, restoring the default.
+    pub fn remove_snapshot_store(&mut self) {
+        self._private_inner_mut().snapshot_store = None;
+    }
+
+    /// Returns the current snapshot store, unless it's the default.
+    pub fn snapshot_store(&self) -> Option<&Arc<dyn SnapshotStore>> {
+        self.inner.snapshot_store.as_ref()
+    }
+
+    /// Sets the engine that renders the differences of failed assertions.
+    ///
+    /// By default the algorithm from the `behavior.diff_algorithm` config
+    /// option is used.  This only affects the output of the assertions
+    /// themselves; `cargo insta review` always uses the configured algorithm.
+    pub fn set_diff_engine(&mut self, engine: Arc<dyn DiffEngine>) {
+        self._private_inner_mut().diff_engine(engine);
+    }
+
+    /// Removes the diff engine, restoring the default.
+    pub fn remove_diff_engine(&mut self) {
+        self._private_inner_mut().diff_engine = None;
+    }
+
+    /// Returns the current diff engine, unless it's the default.
+    pub fn diff_engine(&self) -> Option<&Arc<dyn DiffEngine>> {
+        self.inner.diff_engine.as_ref()
+    }
+
+    /// Sets how snapshots are written, overriding the insta config file.
+    ///
+    /// This is useful to pick a default for a suite, for instance to never
+    /// write snapshots for one that is checked by a separate process:
+    ///
+    /// ```
+    /// use insta::{Settings, SnapshotUpdate};
+    ///
+    /// let mut settings = Settings::clone_current();
+    /// settings.set_snapshot_update(SnapshotUpdate::No);
+    /// ```
+    ///
+    /// An explicitly set `INSTA_UPDATE` (which `cargo insta test` sets), an
+    /// update mode of `no`, and running on CI still take precedence, so that
+    /// a test cannot write snapshots in place where this is not wanted.
+    pub fn set_snapshot_update(&mut self, value: SnapshotUpdate) {
+        self._private_inner_mut().snapshot_update(value);
+    }
+
+    /// Removes the update mode, restoring the one from `INSTA_UPDATE`.
+    pub fn remove_snapshot_update(&mut self) {
+        self._private_inner_mut().snapshot_update = None;
+    }
+
+    /// Returns the current update mode, unless it comes from `INSTA_UPDATE`.
+    pub fn snapshot_update(&self) -> Option<SnapshotUpdate> {
+        self.inner.snapshot_update
     }
 
     /// Runs a function with the current settings bound to the thread.
@@ -523,15 +691,21 @@ impl Settings {
     /// # }
     /// ```
     pub fn bind_async<F: Future<Output = T>, T>(&self, future: F) -> impl Future<Output = T> {
-        #[pin_project::pin_project]
-        struct BindingFuture<F>(Arc<ActualSettings>, #[pin] F);
+        pin_project_lite::pin_project! {
+            struct BindingFuture<F> {
+                settings: Arc<ActualSettings>,
+                #[pin]
+                future: F,
+            }
+        }
 
         impl<F: Future> Future for BindingFuture<F> {
             type Output = F::Output;
 
             fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
-                let inner = self.0.clone();
-                let future = self.project().1;
+                let this = self.project();
+                let inner = this.settings.clone();
+                let future = this.future;
                 CURRENT_SETTINGS.with(|x| {
                     let old = {
                         let mut current = x.borrow_mut();
@@ -547,7 +721,10 @@ impl Settings {
             }
         }
 
-        BindingFuture(self.inner.clone(), future)
+        BindingFuture {
+            settings: self.inner.clone(),
+            future,
+        }
     }
 
     /// Binds the settings to the current thread and resets when the drop
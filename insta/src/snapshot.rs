@@ -1,17 +1,49 @@
 use crate::{
     content::{self, json, yaml, Content},
     elog,
+    store::{FileSystemStore, SnapshotStore},
     utils::style,
 };
 use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
 use std::env;
 use std::error::Error;
 use std::fs;
-use std::io::{BufRead, BufReader, Write};
+use std::io::{self, BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
-use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
-use std::{borrow::Cow, fmt};
+use std::{borrow::Cow, fmt, mem};
+
+/// Snapshot files already read by this process, keyed by path.
+static SNAPSHOT_CACHE: Lazy<Mutex<BTreeMap<PathBuf, CachedSnapshot>>> = Lazy::new(Default::default);
+
+/// Snapshot files larger than this (in bytes) are not cached.
+const MAX_CACHED_SNAPSHOT_SIZE: u64 = 1024 * 1024;
+
+struct CachedSnapshot {
+    modified: SystemTime,
+    len: u64,
+    snapshot: Snapshot,
+}
+
+/// Forgets the cached snapshot for a path that is about to be written.
+pub(crate) fn evict_cached_snapshot(path: &Path) {
+    SNAPSHOT_CACHE.lock().unwrap().remove(path);
+}
+
+/// The version of the snapshot file format written by this version of insta.
+///
+/// - `1`: the metadata are `key: value` lines, ended by an empty line.
+/// - `2`: the metadata are a YAML document between `---` lines.
+///
+/// Snapshots are always written in the latest version.  A version is only
+/// recorded in the file (as `format_version` in the metadata) once it is
+/// newer than `2`, so that existing snapshots do not change.
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 2;
+
+/// The oldest format version that is recorded in the snapshot files.
+const FIRST_RECORDED_FORMAT_VERSION: u32 = 3;
 
 static RUN_ID: Lazy<String> = Lazy::new(|| {
     if let Ok(run_id) = env::var("NEXTEST_RUN_ID") {
@@ -72,17 +104,29 @@ impl PendingInlineSnapshot {
         Ok(())
     }
 
+    #[cfg(feature = "_cargo_insta_internal")]
     pub fn save(&self, p: &Path) -> Result<(), Box<dyn Error>> {
-        let mut f = fs::OpenOptions::new().create(true).append(true).open(p)?;
+        self.save_to(p, &FileSystemStore::new())
+    }
+
+    /// Appends the snapshot to the pending file in the given store.  The
+    /// line is appended in one go so that parallel tests appending to the
+    /// same file do not interleave.
+    pub(crate) fn save_to(
+        &self,
+        p: &Path,
+        store: &dyn SnapshotStore,
+    ) -> Result<(), Box<dyn Error>> {
         let mut s = json::to_string(&self.as_content());
         s.push('\n');
-        f.write_all(s.as_bytes())?;
+        store.append(p, s.as_bytes())?;
         Ok(())
     }
 
     #[cfg(feature = "_cargo_insta_internal")]
-    fn from_content(content: Content) -> Result<PendingInlineSnapshot, Box<dyn Error>> {
-        if let Content::Map(map) = content {
+    fn from_content(mut content: Content) -> Result<PendingInlineSnapshot, Box<dyn Error>> {
+        if let Content::Map(ref mut map) = content {
+            let map = mem::take(map);
             let mut run_id = None;
             let mut line = None;
             let mut old = None;
@@ -212,8 +256,9 @@ impl MetaData {
         self.input_file.as_deref()
     }
 
-    fn from_content(content: Content) -> Result<MetaData, Box<dyn Error>> {
-        if let Content::Map(map) = content {
+    fn from_content(mut content: Content) -> Result<MetaData, Box<dyn Error>> {
+        if let Content::Map(ref mut map) = content {
+            let map = mem::take(map);
             let mut source = None;
             let mut assertion_line = None;
             let mut description = None;
@@ -333,17 +378,72 @@ pub struct Snapshot {
     snapshot_name: Option<String>,
     metadata: MetaData,
     snapshot: SnapshotContents,
+    format_version: u32,
 }
 
 impl Snapshot {
     /// Loads a snapshot from a file.
     pub fn from_file(p: &Path) -> Result<Snapshot, Box<dyn Error>> {
-        let mut f = BufReader::new(fs::File::open(p)?);
+        let snapshot =
+            Snapshot::from_reader(p, BufReader::new(fs::File::open(p)?), |path| fs::read(path))?;
+        snapshot.warn_if_outdated(p);
+        Ok(snapshot)
+    }
+
+    /// Loads a snapshot from the contents of the file at `p`.
+    ///
+    /// The contents of binary snapshots are loaded with `read_binary`.
+    pub(crate) fn from_bytes<F>(
+        p: &Path,
+        data: &[u8],
+        read_binary: F,
+    ) -> Result<Snapshot, Box<dyn Error>>
+    where
+        F: FnOnce(&Path) -> io::Result<Vec<u8>>,
+    {
+        let snapshot = Snapshot::from_reader(p, data, read_binary)?;
+        snapshot.warn_if_outdated(p);
+        Ok(snapshot)
+    }
+
+    fn warn_if_outdated(&self, p: &Path) {
+        if self.format_version < SNAPSHOT_FORMAT_VERSION {
+            elog!("A snapshot uses a legacy snapshot format; please update it to the new format with `cargo insta migrate`.\nSnapshot is at: {}", p.to_string_lossy());
+        }
+    }
+
+    /// Rewrites a snapshot file in the latest format.
+    ///
+    /// Besides the format itself this drops metadata that is no longer
+    /// written and normalizes the contents, exactly like accepting the
+    /// snapshot again would.  Returns whether the file changed.
+    #[doc(hidden)]
+    #[cfg(feature = "_cargo_insta_internal")]
+    pub fn migrate_file(p: &Path) -> Result<bool, Box<dyn Error>> {
+        let data = fs::read(p).map_err(|e| content::Error::FileIo(e, p.to_path_buf()))?;
+        let snapshot = Snapshot::from_reader(p, &data[..], |path| fs::read(path))?;
+        let mut migrated = Vec::new();
+        snapshot.write_snapshot(&snapshot.metadata.trim_for_persistence(), &mut migrated)?;
+        if migrated == data {
+            return Ok(false);
+        }
+        FileSystemStore::new()
+            .write(p, &mut |w| w.write_all(&migrated))
+            .map_err(|e| content::Error::FileIo(e, p.to_path_buf()))?;
+        Ok(true)
+    }
+
+    fn from_reader<R, F>(p: &Path, mut f: R, read_binary: F) -> Result<Snapshot, Box<dyn Error>>
+    where
+        R: BufRead,
+        F: FnOnce(&Path) -> io::Result<Vec<u8>>,
+    {
         let mut buf = String::new();
 
         f.read_line(&mut buf)?;
 
         // yaml format
+        let mut format_version = SNAPSHOT_FORMAT_VERSION;
         let metadata = if buf.trim_end() == "---" {
             loop {
                 let read = f.read_line(&mut buf)?;
@@ -356,6 +456,25 @@ impl Snapshot {
                 }
             }
             let content = yaml::parse_str(&buf, p)?;
+            let version = match content {
+                Content::Map(ref map) => map
+                    .iter()
+                    .find(|x| x.0.as_str() == Some("format_version"))
+                    .and_then(|x| x.1.as_u64()),
+                _ => None,
+            };
+            if let Some(version) = version {
+                if version > SNAPSHOT_FORMAT_VERSION as u64 {
+                    return Err(format!(
+                        "snapshot {} uses format version {}, but this version of insta only supports up to {}",
+                        p.display(),
+                        version,
+                        SNAPSHOT_FORMAT_VERSION
+                    )
+                    .into());
+                }
+                format_version = version as u32;
+            }
             MetaData::from_content(content)?
         // legacy format
         // (but not viable to move into `match_legacy` given it's more than
@@ -381,7 +500,7 @@ impl Snapshot {
                     }
                 }
             }
-            elog!("A snapshot uses a legacy snapshot format; please update it to the new format with `cargo insta test --force-update-snapshots --accept`.\nSnapshot is at: {}", p.to_string_lossy());
+            format_version = 1;
             rv
         };
 
@@ -404,20 +523,56 @@ impl Snapshot {
             }
             SnapshotKind::Binary { ref extension } => {
                 let path = build_binary_path(extension, p);
-                let contents = fs::read(path)?;
+                let contents = read_binary(&path)?;
 
-                SnapshotContents::Binary(Rc::new(contents))
+                SnapshotContents::Binary(Arc::new(contents))
             }
         };
 
         let (snapshot_name, module_name) = names_of_path(p);
 
-        Ok(Snapshot::from_components(
-            module_name,
-            Some(snapshot_name),
-            metadata,
-            contents,
-        ))
+        Ok(Snapshot {
+            format_version,
+            ..Snapshot::from_components(module_name, Some(snapshot_name), metadata, contents)
+        })
+    }
+
+    /// Same as [`Self::from_file`] but memoizes the parsed snapshot.
+    ///
+    /// Tests asserting against the same snapshot file many times only read
+    /// and parse it once.  The cached snapshot is discarded if the file's
+    /// modification time or size changed since.
+    pub(crate) fn from_file_cached(p: &Path) -> Result<Snapshot, Box<dyn Error>> {
+        let stamp = fs::metadata(p).and_then(|md| Ok((md.modified()?, md.len())));
+        let (modified, len) = match stamp {
+            Ok(stamp) => stamp,
+            // no reliable modification time, don't cache
+            Err(_) => return Snapshot::from_file(p),
+        };
+
+        if let Some(cached) = SNAPSHOT_CACHE.lock().unwrap().get(p) {
+            if cached.modified == modified && cached.len == len {
+                return Ok(cached.snapshot.clone());
+            }
+        }
+
+        let snapshot = Snapshot::from_file(p)?;
+        // keep large snapshots out of the cache so that a suite with many of
+        // them does not hold all of them in memory for the whole run.
+        let mut cache = SNAPSHOT_CACHE.lock().unwrap();
+        if len > MAX_CACHED_SNAPSHOT_SIZE || snapshot.contents().is_binary() {
+            cache.remove(p);
+        } else {
+            cache.insert(
+                p.to_path_buf(),
+                CachedSnapshot {
+                    modified,
+                    len,
+                    snapshot: snapshot.clone(),
+                },
+            );
+        }
+        Ok(snapshot)
     }
 
     pub(crate) fn from_components(
@@ -431,12 +586,38 @@ impl Snapshot {
             snapshot_name,
             metadata,
             snapshot,
+            format_version: SNAPSHOT_FORMAT_VERSION,
         }
     }
 
+    /// Creates a file snapshot with text contents, for tools that write
+    /// snapshot files themselves.
+    #[doc(hidden)]
     #[cfg(feature = "_cargo_insta_internal")]
-    fn from_content(content: Content, kind: TextSnapshotKind) -> Result<Snapshot, Box<dyn Error>> {
-        if let Content::Map(map) = content {
+    pub fn from_text(
+        module_name: &str,
+        snapshot_name: &str,
+        info: Option<Content>,
+        contents: String,
+    ) -> Snapshot {
+        Snapshot::from_components(
+            module_name.to_string(),
+            Some(snapshot_name.to_string()),
+            MetaData {
+                info,
+                ..MetaData::default()
+            },
+            TextSnapshotContents::new(contents, TextSnapshotKind::File).into(),
+        )
+    }
+
+    #[cfg(feature = "_cargo_insta_internal")]
+    fn from_content(
+        mut content: Content,
+        kind: TextSnapshotKind,
+    ) -> Result<Snapshot, Box<dyn Error>> {
+        if let Content::Map(ref mut map) = content {
+            let map = mem::take(map);
             let mut module_name = None;
             let mut snapshot_name = None;
             let mut metadata = None;
@@ -468,6 +649,7 @@ impl Snapshot {
                 snapshot_name,
                 metadata: metadata.ok_or(content::Error::MissingField)?,
                 snapshot: snapshot.ok_or(content::Error::MissingField)?,
+                format_version: SNAPSHOT_FORMAT_VERSION,
             })
         } else {
             Err(content::Error::UnexpectedDataType.into())
@@ -510,6 +692,14 @@ impl Snapshot {
         &self.snapshot
     }
 
+    /// The version of the file format the snapshot was read from.
+    ///
+    /// This is [`SNAPSHOT_FORMAT_VERSION`] for snapshots that were not read
+    /// from a file.
+    pub fn format_version(&self) -> u32 {
+        self.format_version
+    }
+
     /// Snapshot contents match another snapshot's.
     pub fn matches(&self, other: &Self) -> bool {
         self.contents() == other.contents()
@@ -539,9 +729,9 @@ impl Snapshot {
                 let contents_match_exact = self_contents.matches_latest(other_contents);
                 match self_contents.kind {
                     TextSnapshotKind::File => {
-                        self.metadata.trim_for_persistence()
-                            == other.metadata.trim_for_persistence()
-                            && contents_match_exact
+                        contents_match_exact
+                            && self.metadata.trim_for_persistence()
+                                == other.metadata.trim_for_persistence()
                     }
                     TextSnapshotKind::Inline => contents_match_exact,
                 }
@@ -550,33 +740,47 @@ impl Snapshot {
         }
     }
 
-    fn serialize_snapshot(&self, md: &MetaData) -> String {
-        let mut buf = yaml::to_string(&md.as_content());
-        buf.push_str("---\n");
+    fn write_snapshot(&self, md: &MetaData, w: &mut dyn Write) -> io::Result<()> {
+        let mut header = md.as_content();
+        if SNAPSHOT_FORMAT_VERSION >= FIRST_RECORDED_FORMAT_VERSION {
+            if let Content::Struct(_, ref mut fields) = header {
+                fields.insert(
+                    0,
+                    ("format_version", Content::from(SNAPSHOT_FORMAT_VERSION)),
+                );
+            }
+        }
+        w.write_all(yaml::to_string(&header).as_bytes())?;
+        w.write_all(b"---\n")?;
 
         if let SnapshotContents::Text(ref contents) = self.snapshot {
-            buf.push_str(&contents.to_string());
-            buf.push('\n');
+            w.write_all(contents.normalize().as_bytes())?;
+            w.write_all(b"\n")?;
         }
 
-        buf
+        Ok(())
     }
 
     // We take `md` as an argument here because the calling methods want to
     // adjust it; e.g. removing volatile fields when writing to the final
     // `.snap` file.
-    fn save_with_metadata(&self, path: &Path, md: &MetaData) -> Result<(), Box<dyn Error>> {
-        if let Some(folder) = path.parent() {
-            fs::create_dir_all(folder)?;
-        }
-
-        let serialized_snapshot = self.serialize_snapshot(md);
-        fs::write(path, serialized_snapshot)
+    fn save_with_metadata(
+        &self,
+        path: &Path,
+        md: &MetaData,
+        store: &dyn SnapshotStore,
+    ) -> Result<(), Box<dyn Error>> {
+        // the snapshot contents can be large, so write them out directly
+        // rather than assembling the whole file in memory first.
+        store
+            .write(path, &mut |w| self.write_snapshot(md, w))
             .map_err(|e| content::Error::FileIo(e, path.to_path_buf()))?;
 
         if let SnapshotContents::Binary(ref contents) = self.snapshot {
-            fs::write(self.build_binary_path(path).unwrap(), &**contents)
-                .map_err(|e| content::Error::FileIo(e, path.to_path_buf()))?;
+            let binary_path = self.build_binary_path(path).unwrap();
+            store
+                .write(&binary_path, &mut |w| w.write_all(contents))
+                .map_err(|e| content::Error::FileIo(e, binary_path))?;
         }
 
         Ok(())
@@ -593,18 +797,31 @@ impl Snapshot {
     /// Saves the snapshot.
     #[doc(hidden)]
     pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
-        self.save_with_metadata(path, &self.metadata.trim_for_persistence())
+        self.save_to(path, &FileSystemStore::new())
     }
 
-    /// Same as [`Self::save`] but instead of writing a normal snapshot file this will write
+    /// Same as [`Self::save`] but writes to the given store.
+    pub(crate) fn save_to(
+        &self,
+        path: &Path,
+        store: &dyn SnapshotStore,
+    ) -> Result<(), Box<dyn Error>> {
+        self.save_with_metadata(path, &self.metadata.trim_for_persistence(), store)
+    }
+
+    /// Same as [`Self::save_to`] but instead of writing a normal snapshot file this will write
     /// a `.snap.new` file with additional information.
     ///
     /// The path of the new snapshot file is returned.
-    pub(crate) fn save_new(&self, path: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    pub(crate) fn save_new(
+        &self,
+        path: &Path,
+        store: &dyn SnapshotStore,
+    ) -> Result<PathBuf, Box<dyn Error>> {
         // TODO: should we be the actual extension here rather than defaulting
         // to the standard `.snap`?
         let new_path = path.to_path_buf().with_extension("snap.new");
-        self.save_with_metadata(&new_path, &self.metadata)?;
+        self.save_with_metadata(&new_path, &self.metadata, store)?;
         Ok(new_path)
     }
 }
@@ -614,11 +831,11 @@ impl Snapshot {
 pub enum SnapshotContents {
     Text(TextSnapshotContents),
 
-    // This is in an `Rc` because we need to be able to clone this struct cheaply and the contents
-    // of the `Vec` could be rather large. The reason it's not an `Rc<[u8]>` is because creating one
+    // This is in an `Arc` because we need to be able to clone this struct cheaply and the contents
+    // of the `Vec` could be rather large. The reason it's not an `Arc<[u8]>` is because creating one
     // of those would require re-allocating because of the additional size needed for the reference
-    // count.
-    Binary(Rc<Vec<u8>>),
+    // count.  It's not an `Rc` so that snapshots can be cached across test threads.
+    Binary(Arc<Vec<u8>>),
 }
 
 // Could be Cow, but I think limited savings
@@ -651,7 +868,10 @@ impl TextSnapshotContents {
 
     /// Snapshot matches based on the latest format.
     pub fn matches_latest(&self, other: &Self) -> bool {
-        self.to_string() == other.to_string()
+        // Normalization only depends on the contents and the kind, so
+        // identical raw contents match without normalizing anything.
+        (self.kind == other.kind && self.contents == other.contents)
+            || self.normalize() == other.normalize()
     }
 
     pub fn matches_legacy(&self, other: &Self) -> bool {
@@ -671,16 +891,26 @@ impl TextSnapshotContents {
         as_str_legacy(self) == as_str_legacy(other)
     }
 
-    fn normalize(&self) -> String {
-        let kind_specific_normalization = match self.kind {
-            TextSnapshotKind::Inline => normalize_inline_snapshot(&self.contents),
-            TextSnapshotKind::File => self.contents.clone(),
-        };
-        // Then this we do for both kinds
-        let out = kind_specific_normalization
-            .trim_start_matches(['\r', '\n'])
-            .trim_end();
-        out.replace("\r\n", "\n")
+    fn normalize(&self) -> Cow<'_, str> {
+        fn trim(s: &str) -> &str {
+            s.trim_start_matches(['\r', '\n']).trim_end()
+        }
+        fn unify_newlines(s: &str) -> Cow<'_, str> {
+            if s.contains("\r\n") {
+                Cow::Owned(s.replace("\r\n", "\n"))
+            } else {
+                Cow::Borrowed(s)
+            }
+        }
+
+        // File snapshots are normalized without a copy unless they need
+        // their newlines rewritten; these can be large.
+        match self.kind {
+            TextSnapshotKind::Inline => Cow::Owned(
+                unify_newlines(trim(&normalize_inline_snapshot(&self.contents))).into_owned(),
+            ),
+            TextSnapshotKind::File => unify_newlines(trim(&self.contents)),
+        }
     }
 
     /// Returns the string literal, including `#` delimiters, to insert into a
@@ -736,7 +966,7 @@ impl TextSnapshotContents {
                         .chain(Some(format!("\n{:width$}", "", width = indentation))),
                 );
             } else {
-                out.push_str(contents.as_str());
+                out.push_str(&contents);
             }
             out.push('"');
         }
@@ -1225,6 +1455,72 @@ fn test_parse_yaml_error() {
     assert!(error.contains("bad.yaml"));
 }
 
+#[test]
+fn test_format_version() {
+    let path = Path::new("snapshots/mod__name.snap");
+    let read = |data: &str| Snapshot::from_bytes(path, data.as_bytes(), |_| unreachable!());
+
+    let legacy = read("Created: 2019-01-01T00:00:00Z\nExpression: \"a\"\n\na\n").unwrap();
+    assert_eq!(legacy.format_version(), 1);
+    assert_eq!(legacy.metadata().expression(), Some("\"a\""));
+    let current = read("---\nexpression: \"a\"\n---\na\n").unwrap();
+    assert_eq!(current.format_version(), SNAPSHOT_FORMAT_VERSION);
+
+    let mut written = Vec::new();
+    legacy
+        .write_snapshot(&legacy.metadata, &mut written)
+        .unwrap();
+    let written = std::str::from_utf8(&written).unwrap();
+    // version 2 is not recorded, so existing snapshots stay as they are
+    assert!(!written.contains("format_version"));
+    assert_eq!(read(written).unwrap().format_version(), 2);
+    let explicit = read("---\nformat_version: 2\nexpression: \"a\"\n---\na\n").unwrap();
+    assert_eq!(explicit.format_version(), 2);
+
+    let error = read("---\nformat_version: 99\n---\na\n").unwrap_err();
+    assert!(error.to_string().contains("uses format version 99"));
+}
+
+#[test]
+fn test_from_file_cached() {
+    let dir = std::env::temp_dir().join(format!("insta-from-file-cached-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("test__cached.snap");
+
+    fs::write(&path, "---\nsource: foo.rs\n---\nfirst\n").unwrap();
+    let snapshot = Snapshot::from_file_cached(&path).unwrap();
+    assert_eq!(
+        snapshot.contents(),
+        &TextSnapshotContents::new("first".into(), TextSnapshotKind::File).into()
+    );
+    assert!(SNAPSHOT_CACHE.lock().unwrap().contains_key(&path));
+
+    // a changed file is picked up again
+    fs::write(&path, "---\nsource: foo.rs\n---\nsecond value\n").unwrap();
+    let snapshot = Snapshot::from_file_cached(&path).unwrap();
+    assert_eq!(
+        snapshot.contents(),
+        &TextSnapshotContents::new("second value".into(), TextSnapshotKind::File).into()
+    );
+
+    // large snapshots are not cached
+    fs::write(
+        &path,
+        format!(
+            "---\nsource: foo.rs\n---\n{}\n",
+            "x".repeat(2 * 1024 * 1024)
+        ),
+    )
+    .unwrap();
+    Snapshot::from_file_cached(&path).unwrap();
+    assert!(!SNAPSHOT_CACHE.lock().unwrap().contains_key(&path));
+
+    // saving evicts the entry
+    snapshot.save(&path).unwrap();
+    assert!(!SNAPSHOT_CACHE.lock().unwrap().contains_key(&path));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
 /// Check that snapshots don't take ownership of the value
 #[test]
 fn test_ownership() {
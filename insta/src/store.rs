@@ -0,0 +1,408 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::elog;
+use crate::snapshot::{evict_cached_snapshot, Snapshot};
+
+/// Storage for snapshot files.
+///
+/// The runtime reads existing snapshots and writes new and pending snapshots
+/// through a store.  Paths are the same as they would be on disk, and files
+/// hold the same contents as they would on disk.
+///
+/// By default snapshots are stored on the file system ([`FileSystemStore`]).
+/// A different store can be set with [`Settings::set_snapshot_store`]; for
+/// instance a [`MemoryStore`] to test helpers that make snapshot assertions
+/// without touching the source tree.
+///
+/// [`Settings::set_snapshot_store`]: crate::Settings::set_snapshot_store
+pub trait SnapshotStore: Send + Sync {
+    /// Reads the file at `path`, or returns `None` if it does not exist.
+    fn read(&self, path: &Path) -> io::Result<Option<Vec<u8>>>;
+
+    /// Replaces the file at `path` with what `contents` writes.
+    fn write(
+        &self,
+        path: &Path,
+        contents: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>,
+    ) -> io::Result<()>;
+
+    /// Appends `data` to the file at `path`, creating it if needed.
+    ///
+    /// Parallel tests append to the same pending snapshot file, so the data
+    /// should be appended in one go.
+    fn append(&self, path: &Path, data: &[u8]) -> io::Result<()>;
+
+    /// Removes the file at `path`.  Removing a missing file is not an error.
+    fn remove(&self, path: &Path) -> io::Result<()>;
+
+    /// Lists the files in the directory `dir` (not recursively).
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>>;
+
+    /// Checks if there is a file at `path`.
+    fn exists(&self, path: &Path) -> bool {
+        matches!(self.read(path), Ok(Some(_)))
+    }
+
+    /// Loads the snapshot at `path`, or returns `None` if it does not exist.
+    fn load(&self, path: &Path) -> Result<Option<Snapshot>, Box<dyn Error>> {
+        match self.read(path)? {
+            Some(data) => Snapshot::from_bytes(path, &data, |p| {
+                self.read(p)?
+                    .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "binary file not found"))
+            })
+            .map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Stores snapshots as files on disk.
+///
+/// This is the default store.  Files are replaced atomically, so parallel
+/// test runs never observe partially written snapshots.
+#[derive(Debug, Default, Clone)]
+pub struct FileSystemStore {
+    fsync: bool,
+}
+
+impl FileSystemStore {
+    /// Creates a store for the file system.
+    pub fn new() -> FileSystemStore {
+        FileSystemStore::default()
+    }
+
+    /// Syncs written files to disk before returning.
+    ///
+    /// The default store does this if the `behavior.fsync` config option
+    /// is set.
+    pub fn set_fsync(&mut self, value: bool) {
+        self.fsync = value;
+    }
+
+    /// Returns the current value for syncing written files.
+    pub fn fsync(&self) -> bool {
+        self.fsync
+    }
+}
+
+impl SnapshotStore for FileSystemStore {
+    fn read(&self, path: &Path) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(path) {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn write(
+        &self,
+        path: &Path,
+        contents: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>,
+    ) -> io::Result<()> {
+        if let Some(folder) = path.parent() {
+            fs::create_dir_all(folder)?;
+        }
+
+        // the modification time might not change when writing the file again
+        // quickly, so don't rely on it for our own writes.
+        evict_cached_snapshot(path);
+
+        write_atomically(path, self.fsync, contents)
+    }
+
+    fn append(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        let mut f = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        f.write_all(data)?;
+        if self.fsync {
+            f.sync_data()?;
+        }
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        match fs::remove_file(path) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        fs::read_dir(dir)?
+            .map(|entry| entry.map(|x| x.path()))
+            .collect()
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        fs::metadata(path).is_ok()
+    }
+
+    fn load(&self, path: &Path) -> Result<Option<Snapshot>, Box<dyn Error>> {
+        if self.exists(path) {
+            Snapshot::from_file_cached(path).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Keeps snapshots in memory.
+///
+/// Useful for hermetic tests of code that makes snapshot assertions: the
+/// store can be prepared with existing snapshots and inspected afterwards,
+/// and nothing is written to the source tree.
+///
+/// ```
+/// use insta::{MemoryStore, Settings};
+/// use std::sync::Arc;
+///
+/// let store = Arc::new(MemoryStore::new());
+/// let mut settings = Settings::clone_current();
+/// settings.set_snapshot_store(store.clone());
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    files: Mutex<BTreeMap<PathBuf, Vec<u8>>>,
+}
+
+impl MemoryStore {
+    /// Creates an empty store.
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    /// Returns the contents of the file at `path`.
+    pub fn get(&self, path: &Path) -> Option<Vec<u8>> {
+        self.files.lock().unwrap().get(path).cloned()
+    }
+
+    /// Sets the contents of the file at `path`.
+    pub fn insert<P: Into<PathBuf>, D: Into<Vec<u8>>>(&self, path: P, data: D) {
+        self.files.lock().unwrap().insert(path.into(), data.into());
+    }
+
+    /// Returns the paths of all files in the store.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.files.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+impl SnapshotStore for MemoryStore {
+    fn read(&self, path: &Path) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.get(path))
+    }
+
+    fn write(
+        &self,
+        path: &Path,
+        contents: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let mut data = Vec::new();
+        contents(&mut data)?;
+        self.insert(path, data);
+        Ok(())
+    }
+
+    fn append(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        self.files
+            .lock()
+            .unwrap()
+            .entry(path.to_path_buf())
+            .or_default()
+            .extend_from_slice(data);
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.files.lock().unwrap().remove(path);
+        Ok(())
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        Ok(self
+            .files
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|x| x.parent() == Some(dir))
+            .cloned()
+            .collect())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.lock().unwrap().contains_key(path)
+    }
+}
+
+/// Reads snapshots from another store but never changes it.
+///
+/// Writes and removals are reported on stderr instead of being carried out.
+/// This is the store used in read-only mode (the `behavior.read_only` config
+/// option), such as for tests run by `bazel test` where the source tree is
+/// not writable.
+pub struct ReadOnlyStore {
+    inner: Arc<dyn SnapshotStore>,
+    report: bool,
+}
+
+impl ReadOnlyStore {
+    /// Creates a store that reads from `inner`.
+    pub fn new(inner: Arc<dyn SnapshotStore>) -> ReadOnlyStore {
+        ReadOnlyStore {
+            inner,
+            report: true,
+        }
+    }
+
+    /// Enables or disables reporting the skipped writes and removals.
+    ///
+    /// This is on by default and turned off for `INSTA_OUTPUT=none`.
+    pub fn set_report(&mut self, yes: bool) {
+        self.report = yes;
+    }
+
+    fn report(&self, action: &str, path: &Path) {
+        if self.report {
+            elog!("read-only: would {} {}", action, path.display());
+        }
+    }
+}
+
+impl fmt::Debug for ReadOnlyStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadOnlyStore").finish_non_exhaustive()
+    }
+}
+
+impl SnapshotStore for ReadOnlyStore {
+    fn read(&self, path: &Path) -> io::Result<Option<Vec<u8>>> {
+        self.inner.read(path)
+    }
+
+    fn write(
+        &self,
+        path: &Path,
+        _contents: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>,
+    ) -> io::Result<()> {
+        self.report("write", path);
+        Ok(())
+    }
+
+    fn append(&self, path: &Path, _data: &[u8]) -> io::Result<()> {
+        self.report("write", path);
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        // passing assertions remove pending snapshots that usually do not
+        // exist, which is not worth reporting.
+        if self.inner.exists(path) {
+            self.report("remove", path);
+        }
+        Ok(())
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        self.inner.list(dir)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.inner.exists(path)
+    }
+
+    fn load(&self, path: &Path) -> Result<Option<Snapshot>, Box<dyn Error>> {
+        self.inner.load(path)
+    }
+}
+
+/// Writes a file by writing to a temporary file next to it first and then
+/// moving that into place.
+///
+/// Tests running in parallel (or several test binaries) can end up writing
+/// the same `.snap.new` file.  With the rename no reader or writer ever sees
+/// a partially written file; the last writer wins.
+fn write_atomically(
+    path: &Path,
+    fsync: bool,
+    write: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>,
+) -> io::Result<()> {
+    static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let temp_path = path.with_file_name(format!(
+        ".{}.{}-{}.tmp",
+        path.file_name()
+            .and_then(|x| x.to_str())
+            .unwrap_or("snapshot"),
+        std::process::id(),
+        TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed),
+    ));
+
+    let rv = (|| {
+        let mut file = BufWriter::new(fs::File::create(&temp_path)?);
+        write(&mut file)?;
+        let file = file.into_inner().map_err(|e| e.into_error())?;
+        if fsync {
+            file.sync_all()?;
+        }
+        drop(file);
+        fs::rename(&temp_path, path)?;
+        if fsync {
+            // persist the rename as well.  This is not supported on all
+            // platforms (eg: opening directories fails on windows).
+            if let Some(dir) = path.parent() {
+                fs::File::open(dir).and_then(|x| x.sync_all()).ok();
+            }
+        }
+        Ok(())
+    })();
+
+    if rv.is_err() {
+        fs::remove_file(&temp_path).ok();
+    }
+    rv
+}
+
+#[test]
+fn test_write_atomically() {
+    let dir = std::env::temp_dir().join(format!("insta-write-atomically-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("test.snap.new");
+
+    write_atomically(&path, false, &mut |w| w.write_all(b"first")).unwrap();
+    write_atomically(&path, true, &mut |w| w.write_all(b"second")).unwrap();
+    let err = write_atomically(&path, false, &mut |_| Err(io::ErrorKind::Other.into()));
+    assert!(err.is_err());
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+    // no temporary files are left behind, even on failure
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_read_only_store() {
+    let inner = Arc::new(MemoryStore::new());
+    inner.insert("a.snap", "old");
+    let store = ReadOnlyStore::new(inner.clone());
+
+    store
+        .write(Path::new("a.snap"), &mut |w| w.write_all(b"new"))
+        .unwrap();
+    store.append(Path::new("b.snap.new"), b"new").unwrap();
+    store.remove(Path::new("a.snap")).unwrap();
+
+    assert_eq!(
+        store.read(Path::new("a.snap")).unwrap(),
+        Some(b"old".to_vec())
+    );
+    assert_eq!(inner.paths(), vec![PathBuf::from("a.snap")]);
+}
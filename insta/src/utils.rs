@@ -15,6 +15,15 @@ pub fn is_ci() -> bool {
     }
 }
 
+/// Can snapshots be read from and written to the file system?
+///
+/// On `wasm32-unknown-unknown` every file system call fails, so there is no
+/// default snapshot store there.  Unless a store is set, mismatches are only
+/// reported through the panic message.
+pub fn has_file_system() -> bool {
+    !cfg!(all(target_arch = "wasm32", target_os = "unknown"))
+}
+
 #[cfg(feature = "colors")]
 pub use console::style;
 
@@ -119,7 +128,6 @@ pub fn get_cargo() -> std::ffi::OsString {
 
 #[test]
 fn test_format_rust_expression() {
-    use crate::assert_snapshot;
     assert_snapshot!(format_rust_expression("vec![1,2,3]"), @"vec![1, 2, 3]");
     assert_snapshot!(format_rust_expression("vec![1,2,3].iter()"), @"vec![1, 2, 3].iter()");
     assert_snapshot!(format_rust_expression(r#"    "aoeu""#), @r###""aoeu""###);
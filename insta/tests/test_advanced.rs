@@ -41,3 +41,29 @@ fn test_basic_duplicates_assertion_failed() {
         }
     }
 }
+
+// The shape of the tests that `rstest` generates for `#[case]` attributes.
+// `test-case` generates the same shape.
+fn test_parameterized_cases(value: u32) {
+    insta::assert_snapshot!(value.to_string());
+    // explicitly named snapshots are shared by all cases
+    insta::assert_snapshot!("parameterized_named", (value > 0).to_string());
+}
+
+mod test_parameterized_cases {
+    #[test]
+    fn case_1() {
+        super::test_parameterized_cases(1);
+    }
+
+    #[test]
+    fn case_2_two() {
+        super::test_parameterized_cases(2);
+    }
+
+    // as generated by `test-case` for `#[test_case(3 ; "three")]`
+    #[test]
+    fn test_case_3_three() {
+        super::test_parameterized_cases(3);
+    }
+}
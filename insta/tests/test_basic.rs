@@ -56,6 +56,25 @@ fn test_unnamed_json_vector() {
     assert_json_snapshot!(vec![1, 2, 3, 4, 5]);
 }
 
+#[cfg(feature = "json")]
+#[test]
+fn test_erased_serialize() {
+    let values: Vec<Box<dyn erased_serde::Serialize>> =
+        vec![Box::new(1), Box::new("two"), Box::new(vec![3])];
+    assert_json_snapshot!(values, @r#"
+    [
+      1,
+      "two",
+      [
+        3
+      ]
+    ]
+    "#);
+
+    let value: &dyn erased_serde::Serialize = &*values[1];
+    assert_json_snapshot!(value, @r#""two""#);
+}
+
 mod nested {
     #[test]
     fn test_nested_module() {
@@ -0,0 +1,47 @@
+#[cfg(feature = "log")]
+use insta::assert_log_snapshot;
+#[cfg(feature = "tracing")]
+use insta::assert_tracing_snapshot;
+
+#[cfg(feature = "tracing")]
+#[test]
+fn test_tracing() {
+    assert_tracing_snapshot!({
+        let span = tracing::info_span!("request", method = "GET", id = tracing::field::Empty);
+        let _guard = span.enter();
+        span.record("id", 7);
+        tracing::debug!(path = "/", "routing");
+        tracing::info_span!("handler").in_scope(|| {
+            tracing::warn!(retries = 2, "slow backend");
+        });
+        tracing::info!(status = 200);
+    }, @r###"
+    DEBUG request{method="GET" id=7}: test_capture: routing path="/"
+    WARN request{method="GET" id=7}:handler: test_capture: slow backend retries=2
+    INFO request{method="GET" id=7}: test_capture: status=200
+    "###);
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn test_tracing_other_thread() {
+    assert_tracing_snapshot!({
+        tracing::info!("before");
+        std::thread::spawn(|| tracing::info!("elsewhere")).join().unwrap();
+    }, @"INFO test_capture: before");
+}
+
+#[cfg(feature = "log")]
+#[test]
+fn test_log() {
+    assert_log_snapshot!({
+        log::info!("starting");
+        log::error!(target: "db", "connection lost: {}", "timeout");
+        assert_eq!(insta::capture_log(|| log::trace!("nested")), "TRACE test_capture: nested\n");
+        log::debug!("done");
+    }, @r###"
+    INFO test_capture: starting
+    ERROR db: connection lost: timeout
+    DEBUG test_capture: done
+    "###);
+}
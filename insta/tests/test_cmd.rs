@@ -0,0 +1,59 @@
+#![cfg(unix)]
+
+use std::process::Command;
+
+use insta::{assert_cmd_snapshot, cmd};
+
+#[test]
+fn test_cmd() {
+    assert_cmd_snapshot!(cmd!("sh", "-c", "echo hello; echo oops >&2; exit 3"), @r###"
+    success: false
+    exit_code: 3
+    ----- stdout -----
+    hello
+    ----- stderr -----
+    oops
+    "###);
+}
+
+#[test]
+fn test_cmd_stdin() {
+    assert_cmd_snapshot!(cmd!("cat").stdin("from stdin"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    from stdin
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_cmd_normalize() {
+    let dir = std::env::temp_dir();
+    let cmd = cmd!("sh", "-c", r#"echo "$PWD"; echo "$GREETING, $GREETING""#)
+        .current_dir(dir.canonicalize().unwrap())
+        .env("GREETING", "hi")
+        .normalize_cwd()
+        .normalize_env("GREETING");
+    assert_cmd_snapshot!(cmd, @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    [CWD]
+    [GREETING], [GREETING]
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_std_command() {
+    let mut command = Command::new("sh");
+    command.args(["-c", "echo $VALUE"]).env("VALUE", "42");
+    assert_cmd_snapshot!(command, @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    42
+    ----- stderr -----
+    "###);
+}
@@ -0,0 +1,16 @@
+use insta::assert_diagnostics_snapshot;
+
+#[test]
+fn test_mismatched_types() {
+    assert_diagnostics_snapshot!("tests/ui/mismatched_types.rs", @r#"
+    error[E0308]: mismatched types
+     --> $DIR/mismatched_types.rs:2
+      |
+    2 |     let x: u32 = "a";
+      |            ---   ^^^ expected `u32`, found `&str`
+      |            |
+      |            expected due to this
+
+    For more information about this error, try `rustc --explain E0308`.
+    "#);
+}
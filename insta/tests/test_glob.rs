@@ -26,6 +26,47 @@ fn test_globs_follow_links() {
     });
 }
 
+#[test]
+fn test_glob_new_input_does_not_fall_back() {
+    use std::sync::Arc;
+
+    use insta::{MemoryStore, SnapshotUpdate};
+
+    // the environment takes precedence over the settings
+    if std::env::var_os("INSTA_UPDATE").is_some() || std::env::var_os("CI").is_some() {
+        return;
+    }
+
+    let snapshots = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots");
+    let store = Arc::new(MemoryStore::new());
+    // an unrelated snapshot that happens to match the new input
+    store.insert(
+        snapshots.join("test_glob__new_input.snap"),
+        "---\nsource: tests/test_glob.rs\n---\nContents of hello\n",
+    );
+    store.insert(
+        snapshots.join("test_glob__new_input@goodbye.txt.snap"),
+        "---\nsource: tests/test_glob.rs\n---\nContents of goodbye\n",
+    );
+    let mut settings = insta::Settings::clone_current();
+    settings.set_snapshot_store(store.clone());
+    settings.set_snapshot_update(SnapshotUpdate::New);
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        settings.bind(|| {
+            insta::glob!("inputs/*.txt", |path| {
+                let contents = std::fs::read_to_string(path).unwrap();
+                insta::assert_snapshot!("new_input", contents);
+            });
+        });
+    }));
+
+    // the new input is not compared against the unsuffixed snapshot
+    assert!(result.is_err());
+    assert!(store
+        .paths()
+        .contains(&snapshots.join("test_glob__new_input@hello.txt.snap.new")));
+}
+
 #[test]
 #[should_panic(expected = "the glob! macro did not match any files.")]
 fn test_empty_glob_fails() {
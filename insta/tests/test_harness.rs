@@ -0,0 +1,6 @@
+fn main() {
+    insta::harness!("inputs/*.txt", |path| {
+        let contents = std::fs::read_to_string(path).unwrap();
+        insta::assert_snapshot!(&contents);
+    });
+}
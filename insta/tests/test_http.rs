@@ -0,0 +1,62 @@
+#![cfg(feature = "http")]
+
+use insta::assert_http_snapshot;
+
+#[test]
+fn test_json_response() {
+    let response = http::Response::builder()
+        .status(201)
+        .header("x-request-id", "5f2b8c1e")
+        .header("Content-Type", "application/json; charset=utf-8")
+        .header("date", "Wed, 14 Oct 2026 12:00:00 GMT")
+        .header("set-cookie", "session=abc")
+        .header("set-cookie", "theme=dark")
+        .header("content-length", "38")
+        .body(r#"{"id":42,"tags":["a","b"],"meta":{}}"#)
+        .unwrap();
+    assert_http_snapshot!(response, @r###"
+    HTTP/1.1 201 Created
+    content-length: [content-length]
+    content-type: application/json; charset=utf-8
+    date: [date]
+    set-cookie: [set-cookie]
+    set-cookie: [set-cookie]
+    x-request-id: [request-id]
+
+    {
+      "id": 42,
+      "tags": [
+        "a",
+        "b"
+      ],
+      "meta": {}
+    }
+    "###);
+}
+
+#[test]
+fn test_other_responses() {
+    let response = http::Response::builder()
+        .status(404)
+        .header("content-type", "text/plain")
+        .body("not found\n")
+        .unwrap();
+    assert_http_snapshot!(response, @r###"
+    HTTP/1.1 404 Not Found
+    content-type: text/plain
+
+    not found
+    "###);
+
+    let response = http::Response::builder()
+        .body(vec![0xff, 0xfe, 0x00])
+        .unwrap();
+    assert_http_snapshot!(response, @r###"
+    HTTP/1.1 200 OK
+
+    <3 bytes of binary data>
+    "###);
+
+    let response = http::Response::builder().status(204).body("").unwrap();
+    assert_http_snapshot!(response, @"HTTP/1.1 204 No Content");
+}
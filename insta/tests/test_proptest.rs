@@ -0,0 +1,55 @@
+#![cfg(feature = "proptest")]
+
+use insta::assert_proptest_snapshot;
+use proptest::prelude::*;
+
+#[test]
+fn test_property_holds() {
+    assert_proptest_snapshot!(proptest::collection::vec(0..100u32, 0..10), |v| {
+        let mut sorted = v.clone();
+        sorted.sort();
+        prop_assert_eq!(sorted.len(), v.len());
+        Ok(())
+    });
+}
+
+#[test]
+fn test_counterexample() {
+    // without failure persistence, so that no regression file is written
+    let config = proptest::test_runner::Config {
+        failure_persistence: None,
+        ..Default::default()
+    };
+    let result = insta::run_proptest(config, &(0..1000u32, 0..1000u32), |(a, b)| {
+        if a >= 10 && b >= 20 {
+            return Err(TestCaseError::fail("too large"));
+        }
+        Ok(())
+    });
+    insta::assert_snapshot!(result, @r###"
+    input: (
+        10,
+        20,
+    )
+    failure: too large
+    "###);
+}
+
+#[test]
+fn test_accepted_counterexample() {
+    // a known failure passes once its counterexample is accepted
+    assert_proptest_snapshot!(
+        "known_counterexample",
+        config = proptest::test_runner::Config {
+            failure_persistence: None,
+            ..Default::default()
+        },
+        0..1000u32,
+        |a| {
+            if a >= 10 {
+                return Err(TestCaseError::fail("too large"));
+            }
+            Ok(())
+        }
+    );
+}
@@ -30,6 +30,7 @@ fn test_selector_parser() {
     assert_selector_snapshot!("foo_bar_range_from", ".foo.bar[10:]");
     assert_selector_snapshot!("foo_bar_range", ".foo.bar[10:20]");
     assert_selector_snapshot!("foo_bar_deep", ".foo.bar.**");
+    assert_selector_snapshot!("foo_except_id", ".foo.* | .bar, !.foo.id");
 }
 
 #[derive(Serialize)]
@@ -433,6 +434,26 @@ fn test_struct_array_redaction() {
     });
 }
 
+#[cfg(feature = "yaml")]
+#[test]
+fn test_redaction_exclusion() {
+    let user = User {
+        id: 42,
+        username: "john_doe".to_string(),
+        email: Email("john@example.com".to_string()),
+        extra: "".to_string(),
+    };
+
+    assert_yaml_snapshot!(vec![user], {
+        "[].* | [].email, ![].id" => "[redacted]",
+    }, @r#"
+    - id: 42
+      username: "[redacted]"
+      email: "[redacted]"
+      extra: "[redacted]"
+    "#);
+}
+
 #[cfg(feature = "yaml")]
 #[test]
 fn test_map_key_redaction() {
@@ -466,6 +487,21 @@ fn test_map_key_redaction() {
     });
 }
 
+#[cfg(feature = "yaml")]
+#[test]
+fn test_map_key_redaction_collision() {
+    let mut map = std::collections::BTreeMap::new();
+    map.insert("a1", 1);
+    map.insert("a2", 2);
+
+    // the redacted keys are the same, which YAML only allows once
+    assert_yaml_snapshot!(map, {
+        ".$key" => "[k]",
+    }, @r###"
+    "[k]": 2
+    "###);
+}
+
 #[cfg(feature = "json")]
 #[test]
 fn test_ordering() {
@@ -543,3 +579,54 @@ fn test_rounded_redaction() {
         }
     );
 }
+
+#[test]
+fn test_debug_redaction() {
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    struct Session {
+        user: &'static str,
+        started: std::time::SystemTime,
+        tokens: Vec<String>,
+    }
+
+    let session = Session {
+        user: "jane",
+        started: std::time::SystemTime::now(),
+        tokens: vec!["a1b2".into(), "c3d4".into()],
+    };
+    insta::with_settings!({redactions => vec![(".user", "[user]".into())]}, {
+        assert_debug_snapshot!(session, {
+            ".started" => "[started]",
+            ".tokens[]" => insta::dynamic_redaction(|value, _| {
+                assert_eq!(value.as_str().map(|x| x.len()), Some(4));
+                "[token]"
+            }),
+        }, @r#"
+        Session {
+            user: "[user]",
+            started: "[started]",
+            tokens: [
+                "[token]",
+                "[token]",
+            ],
+        }
+        "#);
+    });
+}
+
+#[cfg(feature = "yaml")]
+#[test]
+#[should_panic(expected = "redaction selector '.user.nmae' did not match anything")]
+fn test_strict_redactions() {
+    #[derive(Serialize)]
+    pub struct User {
+        name: &'static str,
+    }
+
+    insta::with_settings!({strict_redactions => true}, {
+        assert_yaml_snapshot!(User { name: "jane" }, {
+            ".user.nmae" => "[name]",
+        }, @"");
+    });
+}
@@ -2,7 +2,10 @@
 use insta::assert_yaml_snapshot;
 use similar_asserts::assert_eq;
 
-use insta::{assert_debug_snapshot, with_settings, Settings};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use insta::{assert_debug_snapshot, with_settings, MemoryStore, Settings};
 
 #[cfg(feature = "yaml")]
 #[test]
@@ -138,3 +141,110 @@ fn test_with_settings_inherit() {
         });
     });
 }
+
+/// Returns settings that keep snapshots in a fresh memory store, along with
+/// the store and the path of the snapshot file `name` in it.
+fn memory_store(name: &str) -> (Settings, Arc<MemoryStore>, PathBuf) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(name);
+    let store = Arc::new(MemoryStore::new());
+    let mut settings = Settings::clone_current();
+    settings.set_snapshot_store(store.clone());
+    (settings, store, path)
+}
+
+#[test]
+fn test_snapshot_store() {
+    let (settings, store, path) = memory_store("test_settings__from_store.snap");
+    store.insert(&path, "---\nsource: tests/test_settings.rs\n---\nstored\n");
+    store.insert(path.with_extension("snap.new"), "stale");
+    settings.bind(|| {
+        insta::assert_snapshot!("from_store", "stored");
+    });
+
+    // the snapshot only exists in the store, and the stale pending snapshot
+    // was cleaned up there.
+    assert!(!path.exists());
+    assert_eq!(store.paths(), vec![path]);
+}
+
+#[test]
+fn test_snapshot_update() {
+    use insta::SnapshotUpdate;
+
+    // the environment takes precedence over the settings
+    if std::env::var_os("INSTA_UPDATE").is_some() || std::env::var_os("CI").is_some() {
+        return;
+    }
+
+    let (mut settings, store, path) = memory_store("test_settings__update_mode.snap");
+    settings.set_snapshot_update(SnapshotUpdate::Always);
+    assert_eq!(settings.snapshot_update(), Some(SnapshotUpdate::Always));
+    settings.bind(|| {
+        insta::assert_snapshot!("update_mode", "written in place");
+    });
+
+    // the new snapshot was accepted right away instead of being put up for
+    // review.
+    assert_eq!(store.paths(), vec![path]);
+}
+
+#[test]
+fn test_snapshot_suffix_fallback() {
+    let (mut settings, store, path) = memory_store("test_settings__shared.snap");
+    store.insert(&path, "---\nsource: tests/test_settings.rs\n---\nshared\n");
+    settings.set_snapshot_suffix("some-platform");
+    settings.bind(|| {
+        insta::assert_snapshot!("shared", "shared");
+    });
+
+    // the shared snapshot matched, so no suffixed one was written.
+    assert_eq!(store.paths(), vec![path]);
+}
+
+#[test]
+fn test_snapshot_suffix_fallback_unseen() {
+    use insta::SnapshotUpdate;
+
+    // the environment takes precedence over the settings
+    if std::env::var_os("INSTA_UPDATE").is_some() || std::env::var_os("CI").is_some() {
+        return;
+    }
+
+    let (mut settings, store, path) = memory_store("test_settings__shared_changed.snap");
+    store.insert(&path, "---\nsource: tests/test_settings.rs\n---\nshared\n");
+    settings.set_snapshot_suffix("some-platform");
+    settings.set_snapshot_update(SnapshotUpdate::Unseen);
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        settings.bind(|| {
+            insta::assert_snapshot!("shared_changed", "changed");
+        });
+    }));
+    assert!(result.is_err());
+
+    // the change to the shared snapshot is put up for review rather than
+    // written in place as a new suffixed snapshot.
+    let suffixed = path.with_file_name("test_settings__shared_changed@some-platform.snap");
+    assert_eq!(
+        store.paths(),
+        vec![path, suffixed.with_extension("snap.new")]
+    );
+}
+
+#[test]
+fn test_snapshot_assertion() {
+    use insta::internals::SnapshotAssertion;
+
+    let (settings, store, path) = memory_store("harness__generated_case.snap");
+    store.insert(
+        &path,
+        "---\nsource: tests/test_settings.rs\n---\ngenerated\n",
+    );
+    SnapshotAssertion::new("generated_case", "generated")
+        .module_path("harness")
+        .with_settings(settings)
+        .assert();
+
+    assert_eq!(store.paths(), vec![path]);
+}
@@ -0,0 +1,3 @@
+pub fn f() {
+    let x: u32 = "a";
+}